@@ -9,5 +9,4 @@
 
 fn main() {
     println!("cargo:rustc-link-search=/usr/local/lib64");
-    println!("cargo:rustc-link-arg=-li2c");
 }
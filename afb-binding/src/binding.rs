@@ -12,9 +12,31 @@
 
 use crate::prelude::*;
 use afbv4::prelude::*;
+use libi2c::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+// afb log level used to report bus transactions
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+pub(crate) enum TraceLevel {
+    ERROR,
+    WARNING,
+    NOTICE,
+    INFO,
+    DEBUG,
+}
 
 pub(crate) struct BindingCfg {
     pub i2cbus: &'static str,
+    pub adapter: Option<&'static str>,
+    pub sysfs: I2cSysfs,
+    pub admin: &'static AfbPermission,
+    pub timeout: Option<Duration>,
+    pub retries: Option<u32>,
+    pub trace: Option<TraceLevel>,
+    pub recovery: Option<I2cRecovery>,
+    pub simulator: Option<JsoncObj>,
     pub devices: JsoncObj,
 }
 
@@ -30,6 +52,84 @@ impl AfbApiControls for BindingCfg {
     }
 }
 
+// build an in-memory bus from 'simulator' config, used to run without /dev/i2c-N
+// -----------------------------------------
+fn simulator_from_config(i2cbus: &'static str, jsim: &JsoncObj) -> Result<I2cSimBus, AfbError> {
+    let mut simulator = I2cSimBus::new(i2cbus);
+
+    for idx in 0..jsim.count()? {
+        let jdev = jsim.index::<JsoncObj>(idx)?;
+        let addr = hexa_string_to_u32(jdev.get::<String>("addr")?)?;
+        let mut device = I2cSimDevice::new(addr);
+
+        // should match device 'reg-width' so raw transfers split pointer and data alike
+        if let Ok(value) = jdev.get::<u32>("reg-width") {
+            let width = match value {
+                8 => I2cRegWidth::BIT8,
+                16 => I2cRegWidth::BIT16,
+                _ => {
+                    return afb_error!(
+                        "i2c-config-fail",
+                        "simulator addr:{:#02x} invalid reg-width:{} should be 8|16", addr, value
+                    )
+                }
+            };
+            let endian = match jdev.get::<String>("reg-endian") {
+                Ok(value) => parse_endian(value)?,
+                Err(_) => I2cEndian::BIG,
+            };
+            device = device.set_reg_width(width, endian);
+        }
+
+        if let Ok(registers) = jdev.get::<JsoncObj>("registers") {
+            for jdx in 0..registers.count()? {
+                let register = registers.index::<JsoncObj>(jdx)?;
+                let reg = hexa_string_to_u16(register.get::<String>("reg")?)?;
+                if let Ok(value) = register.get::<String>("value") {
+                    device = device.set_registers_at(reg, &[hexa_string_to_u8(value)?]);
+                }
+                if let Ok(value) = register.get::<String>("readonly") {
+                    let reg = match u8::try_from(reg) {
+                        Ok(reg) => reg,
+                        Err(_) => {
+                            return afb_error!(
+                                "i2c-config-fail",
+                                "simulator addr:{:#02x} readonly reg:{:#04x} should be 0x00-0xFF", addr, reg
+                            )
+                        }
+                    };
+                    device = device.set_readonly(reg, hexa_string_to_u8(value)?);
+                }
+            }
+        }
+
+        if let Ok(value) = jdev.get::<bool>("nack") {
+            if value {
+                device = device.set_nack(I2cSimNack::ALWAYS);
+            }
+        }
+
+        if let Ok(value) = jdev.get::<String>("driver") {
+            device = device.set_driver(to_static_str(value));
+        }
+
+        if let Ok(value) = jdev.get::<bool>("bad-pec") {
+            device = device.set_bad_pec(value);
+        }
+
+        if let Ok(nacks) = jdev.get::<JsoncObj>("nack-regs") {
+            let mut regs = Vec::new();
+            for jdx in 0..nacks.count()? {
+                regs.push(hexa_string_to_u8(nacks.index::<String>(jdx)?)?);
+            }
+            device = device.set_nack(I2cSimNack::REGISTERS(regs));
+        }
+
+        simulator = simulator.add_device(device);
+    }
+    Ok(simulator)
+}
+
 // Binding init callback started at binding load time before any API exist
 // -----------------------------------------
 pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi, AfbError> {
@@ -59,8 +159,31 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
         AfbPermission::new("acl:i2c:client")
     };
 
-    let i2cbus = if let Ok(value) = jconf.get::<String>("i2cbus") {
-        to_static_str(value)
+    // bus wide verbs (scan) may disturb devices, they require a stronger permission
+    let admin = if let Ok(value) = jconf.get::<String>("admin-permission") {
+        AfbPermission::new(to_static_str(value))
+    } else {
+        AfbPermission::new("acl:i2c:admin")
+    };
+
+    // i2cbus is either a device path '/dev/i2c-N' or {'name':'adapter-name', 'sysfs':'/sys'}
+    let mut sysfs = I2cSysfs::default();
+    let (i2cbus, adapter) = if let Ok(value) = jconf.get::<String>("i2cbus") {
+        (to_static_str(value), None)
+    } else if let Ok(jbus) = jconf.get::<JsoncObj>("i2cbus") {
+        let name = match jbus.get::<String>("name") {
+            Ok(value) => to_static_str(value),
+            Err(_) => {
+                return afb_error!(
+                    "i2c-config-fail",
+                    "'i2cbus' object requires an adapter 'name'",
+                )
+            }
+        };
+        if let Ok(value) = jbus.get::<String>("sysfs") {
+            sysfs = I2cSysfs::new(value.as_str());
+        }
+        (name, Some(name))
     } else {
         return afb_error!(
             "i2c-config-fail",
@@ -68,6 +191,78 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
         )
     };
 
+    // optional adapter settings, applied on every bus (re)open
+    let timeout = if let Ok(value) = jconf.get::<u64>("timeout") {
+        Some(Duration::from_millis(value))
+    } else {
+        None
+    };
+
+    let retries = jconf.get::<u32>("retries").ok();
+
+    // transactions are not logged unless 'trace' defines a log level
+    let trace = if let Ok(value) = jconf.get::<String>("trace") {
+        let level = match value.to_lowercase().as_str() {
+            "error" => TraceLevel::ERROR,
+            "warning" => TraceLevel::WARNING,
+            "notice" => TraceLevel::NOTICE,
+            "info" => TraceLevel::INFO,
+            "debug" => TraceLevel::DEBUG,
+            _ => {
+                return afb_error!(
+                    "i2c-config-fail",
+                    "invalid trace:{} should be error|warning|notice|info|debug", value
+                )
+            }
+        };
+        Some(level)
+    } else {
+        None
+    };
+
+    // bus is only reopened on fatal errors when 'recovery' is set (true or tuning object)
+    let recovery = if let Ok(value) = jconf.get::<bool>("recovery") {
+        if value {
+            Some(I2cRecovery::default())
+        } else {
+            None
+        }
+    } else if let Ok(jrecovery) = jconf.get::<JsoncObj>("recovery") {
+        let mut recovery = I2cRecovery::default();
+        if let Ok(value) = jrecovery.get::<u64>("delay") {
+            recovery.delay = Duration::from_millis(value);
+        }
+        if let Ok(value) = jrecovery.get::<u64>("max-delay") {
+            recovery.max_delay = Duration::from_millis(value);
+        }
+        if let Ok(value) = jrecovery.get::<u32>("eio-threshold") {
+            recovery.eio_threshold = value;
+        }
+        if recovery.delay.is_zero() || recovery.delay > recovery.max_delay {
+            return afb_error!(
+                "i2c-config-fail",
+                "invalid recovery delay:{:?} should be within 1ms-max-delay:{:?}",
+                recovery.delay,
+                recovery.max_delay
+            );
+        }
+        Some(recovery)
+    } else {
+        None
+    };
+
+    let simulator = if let Ok(value) = jconf.get::<JsoncObj>("simulator") {
+        if !matches!(value.get_type(), Jtype::Array) {
+            return afb_error!(
+                "i2c-config-fail",
+                "optional 'simulator' should be an array",
+            )
+        }
+        Some(value)
+    } else {
+        None
+    };
+
     let devices = if let Ok(value) = jconf.get::<JsoncObj>("devices") {
         if !matches!(value.get_type(), Jtype::Array) {
             return afb_error!(
@@ -85,12 +280,38 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
 
     let config = BindingCfg {
         i2cbus,
+        adapter,
+        sysfs,
+        admin,
+        timeout,
+        retries,
+        trace,
+        recovery,
+        simulator,
         devices,
     };
 
     // create backend API
     let api = AfbApi::new(api).set_info(info).set_permission(permission);
-    register_verbs(api, config)?;
+
+    // open i2c bus, device init commands are sent by register_verbs
+    match config.simulator.clone() {
+        Some(jsim) => {
+            afb_log_msg!(Notice, rootv4, "i2cbus:{} running on simulator", config.i2cbus);
+            let simulator = simulator_from_config(config.i2cbus, &jsim)?;
+            let i2c = Arc::new(I2cHandle::from_backend(simulator)?);
+            register_verbs(api, config, i2c)?;
+        }
+        None => {
+            let i2c = match config.adapter {
+                Some(name) => I2cHandle::open_adapter(name, &config.sysfs)?,
+                None => I2cHandle::from_backend(
+                    I2cDevBus::new(config.i2cbus)?.set_sysfs(config.sysfs.clone()),
+                )?,
+            };
+            register_verbs(api, config, Arc::new(i2c))?;
+        }
+    }
 
     Ok(api.finalize()?)
 }
@@ -13,11 +13,12 @@
 use crate::prelude::*;
 use afbv4::prelude::*;
 use libi2c::prelude::*;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time;
 use std::time::Duration;
-use std::{thread, time};
 
-fn hexa_string_to_u32(input: String) -> Result<u32, AfbError> {
+pub(crate) fn hexa_string_to_u32(input: String) -> Result<u32, AfbError> {
     let data = input.trim_start_matches("0x");
     if data != input {
         match u32::from_str_radix(data, 16) {
@@ -32,7 +33,7 @@ fn hexa_string_to_u32(input: String) -> Result<u32, AfbError> {
     }
 }
 
-fn hexa_string_to_u16(input: String) -> Result<u16, AfbError> {
+pub(crate) fn hexa_string_to_u16(input: String) -> Result<u16, AfbError> {
     let data = input.trim_start_matches("0x");
     if data != input {
         match u16::from_str_radix(data, 16) {
@@ -47,7 +48,7 @@ fn hexa_string_to_u16(input: String) -> Result<u16, AfbError> {
     }
 }
 
-fn hexa_string_to_u8(input: String) -> Result<u8, AfbError> {
+pub(crate) fn hexa_string_to_u8(input: String) -> Result<u8, AfbError> {
     let data = input.trim_start_matches("0x");
     if data != input {
         match u8::from_str_radix(data, 16) {
@@ -62,41 +63,329 @@ fn hexa_string_to_u8(input: String) -> Result<u8, AfbError> {
     }
 }
 
-pub(self) fn cmd_exec(
-    i2c: Rc<I2cHandle>,
+// raw bytes hexa string with any even number of digits (eg: 0x0102A0)
+fn hexa_string_to_bytes(input: String) -> Result<Vec<u8>, AfbError> {
+    let data = input.trim_start_matches("0x");
+    if data == input || !data.is_ascii() || !data.len().is_multiple_of(2) {
+        return afb_error!(
+            "hexa-invalid-bytes",
+            "{} should be 0x followed by hexa bytes", input
+        );
+    }
+
+    let mut bytes = Vec::new();
+    for idx in 0..data.len() / 2 {
+        match u8::from_str_radix(&data[2 * idx..2 * idx + 2], 16) {
+            Err(_error) => return afb_error!("hexa-invalid-bytes", input),
+            Ok(value) => bytes.push(value),
+        }
+    }
+    Ok(bytes)
+}
+
+// block value is a single hexa string holding exactly 'size' bytes
+fn hexa_string_to_block(input: String, size: u8) -> Result<Vec<u8>, AfbError> {
+    let block = hexa_string_to_bytes(input.clone())?;
+    if block.len() != size as usize {
+        return afb_error!(
+            "hexa-invalid-block",
+            "{} should hold {} hexa bytes", input, size
+        );
+    }
+    Ok(block)
+}
+
+pub(crate) fn check_data_size(size: u8) -> Result<(), AfbError> {
+    if size == 0 || size as usize > I2C_SMBUS_BLOCK_MAX {
+        return afb_error!(
+            "i2c-data-size",
+            "invalid size:{} should Byte(1), World(2) or Block(3-{})", size, I2C_SMBUS_BLOCK_MAX
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn hexa_string_to_data(input: String, size: u8) -> Result<SmbusData, AfbError> {
+    check_data_size(size)?;
+    let data = match size {
+        1 => SmbusData::BYTE(hexa_string_to_u8(input)?),
+        2 => SmbusData::WORD(hexa_string_to_u16(input)?),
+        _ => SmbusData::BLOCK(hexa_string_to_block(input, size)?),
+    };
+    Ok(data)
+}
+
+// typed values accept raw register bits as hexa '0xFFF6' or a decimal '-10'
+fn string_to_value(input: String, dtype: I2cDataType) -> Result<i64, AfbError> {
+    if input.starts_with("0x") {
+        let raw = hexa_string_to_u32(input)?;
+        dtype.check_raw(raw)?;
+        return Ok(dtype.from_raw(raw));
+    }
+    match input.parse::<i64>() {
+        Err(_error) => afb_error!("hexa-invalid-integer", input),
+        Ok(value) => Ok(value),
+    }
+}
+
+// 'value' is either a json number or a string, encoded following register type and byte order
+fn json_to_data(
+    number: Result<i64, AfbError>,
+    text: Result<String, AfbError>,
+    dtype: I2cDataType,
+    endian: I2cEndian,
+) -> Result<SmbusData, AfbError> {
+    let value = match number {
+        Ok(value) => value,
+        Err(_) => string_to_value(text?, dtype)?,
+    };
+    Ok(dtype.encode(value, endian)?)
+}
+
+fn parse_data_type(input: String) -> Result<I2cDataType, AfbError> {
+    let dtype = match input.to_lowercase().as_str() {
+        "u8" => I2cDataType::U8,
+        "i8" => I2cDataType::I8,
+        "u16" => I2cDataType::U16,
+        "i16" => I2cDataType::I16,
+        "u32" => I2cDataType::U32,
+        "i32" => I2cDataType::I32,
+        _ => {
+            return afb_error!(
+                "i2c-config-fail",
+                "invalid type:{} should be u8|i8|u16|i16|u32|i32", input
+            )
+        }
+    };
+    Ok(dtype)
+}
+
+pub(crate) fn parse_endian(input: String) -> Result<I2cEndian, AfbError> {
+    let endian = match input.to_lowercase().as_str() {
+        "little" => I2cEndian::LITTLE,
+        "big" => I2cEndian::BIG,
+        _ => {
+            return afb_error!(
+                "i2c-config-fail",
+                "invalid endian:{} should be big|little", input
+            )
+        }
+    };
+    Ok(endian)
+}
+
+// reject at config time registers the adapter cannot access
+fn check_data_func<B: I2cBackend>(i2c: &I2cHandle<B>, rw: SmbusRw, size: u8) -> Result<(), AfbError> {
+    let smbus = match size {
+        1 => SmbusSize::BYTE_DATA,
+        2 => SmbusSize::WORD_DATA,
+        _ => SmbusSize::I2C_BLOCK_DATA,
+    };
+    i2c.check_func(I2cFunc::from_smbus(rw, smbus))?;
+    Ok(())
+}
+
+// size>2 registers map onto I2C block transfers of 'size' bytes,
+// 16bit register maps always use combined transfers
+fn i2c_read_data<B: I2cBackend>(
+    i2c: &I2cHandle<B>,
     dev_addr: u32,
+    cmd_reg: u16,
     cmd_size: u8,
-    cmd: JsoncObj,
+) -> Result<SmbusData, AfbError> {
+    check_data_size(cmd_size)?;
+    if i2c.get_addr_cfg(dev_addr).reg_width == I2cRegWidth::BIT16 {
+        let values = i2c.read_at(dev_addr, cmd_reg, cmd_size as usize)?;
+        return Ok(SmbusData::from_bytes(values));
+    }
+    let cmd_reg = cmd_reg as u8;
+    let data = match cmd_size {
+        1 => SmbusData::BYTE(i2c.read(dev_addr, cmd_reg)?),
+        2 => SmbusData::WORD(i2c.read(dev_addr, cmd_reg)?),
+        _ => SmbusData::BLOCK(i2c.read_i2c_block(dev_addr, cmd_reg, cmd_size as usize)?),
+    };
+    Ok(data)
+}
+
+fn i2c_write_data<B: I2cBackend>(
+    i2c: &I2cHandle<B>,
+    dev_addr: u32,
+    cmd_reg: u16,
+    data: &SmbusData,
 ) -> Result<(), AfbError> {
-    let cmd_reg = hexa_string_to_u8(cmd.get::<String>("reg")?)?;
-    let cmd_value = cmd.get::<String>("value")?;
-    match cmd_size {
-        1 => {
-            i2c.write(dev_addr, cmd_reg, hexa_string_to_u8(cmd_value)?)?;
+    if i2c.get_addr_cfg(dev_addr).reg_width == I2cRegWidth::BIT16 && *data != SmbusData::NONE {
+        i2c.write_at(dev_addr, cmd_reg, &data.to_bytes())?;
+        return Ok(());
+    }
+    let cmd_reg = cmd_reg as u8;
+    match data {
+        SmbusData::BYTE(value) => i2c.write(dev_addr, cmd_reg, *value)?,
+        SmbusData::WORD(value) => i2c.write(dev_addr, cmd_reg, *value)?,
+        SmbusData::BLOCK(values) => i2c.write_i2c_block(dev_addr, cmd_reg, values)?,
+        SmbusData::NONE => return afb_error!("i2c-write-data", "addr:{} register:{} no data", dev_addr, cmd_reg),
+    }
+    Ok(())
+}
+
+// only mask bits are changed, read and write happen under the same bus lock
+fn i2c_update_data<B: I2cBackend>(
+    i2c: &I2cHandle<B>,
+    dev_addr: u32,
+    cmd_reg: u16,
+    mask: &SmbusData,
+    data: &SmbusData,
+) -> Result<(), AfbError> {
+    if i2c.get_addr_cfg(dev_addr).reg_width == I2cRegWidth::BIT16 {
+        return afb_error!("i2c-update-data", "addr:{} register:{} mask requires 8bit register", dev_addr, cmd_reg);
+    }
+    let cmd_reg = cmd_reg as u8;
+    match (mask, data) {
+        (SmbusData::BYTE(mask), SmbusData::BYTE(value)) => {
+            i2c.update_bits(dev_addr, cmd_reg, *mask, *value)?;
+        }
+        (SmbusData::WORD(mask), SmbusData::WORD(value)) => {
+            i2c.update_bits(dev_addr, cmd_reg, *mask, *value)?;
+        }
+        _ => return afb_error!("i2c-update-data", "addr:{} register:{} mask only applies to byte or word", dev_addr, cmd_reg),
+    }
+    Ok(())
+}
+
+// mask uses the same encoding as command values (raw hexa, typed with byte order)
+fn mask_to_data(
+    input: String,
+    cmd_type: Option<I2cDataType>,
+    endian: I2cEndian,
+    cmd_size: u8,
+) -> Result<SmbusData, AfbError> {
+    match cmd_type {
+        Some(dtype) => Ok(dtype.encode(string_to_value(input, dtype)?, endian)?),
+        None => hexa_string_to_data(input, cmd_size),
+    }
+}
+
+// device init command, kept to be replayed after bus recovery
+struct InitCmd {
+    dev_uid: &'static str,
+    dev_addr: u32,
+    reg: u16,
+    value: SmbusData,
+}
+
+fn init_parse<B: I2cBackend>(
+    i2c: &I2cHandle<B>,
+    dev_uid: &'static str,
+    dev_addr: u32,
+    cmd_size: u8,
+    cmd: JsoncObj,
+) -> Result<InitCmd, AfbError> {
+    let reg = hexa_string_to_u16(cmd.get::<String>("reg")?)?;
+    i2c.check_reg(dev_addr, reg)?;
+    let value = hexa_string_to_data(cmd.get::<String>("value")?, cmd_size)?;
+    Ok(InitCmd {
+        dev_uid,
+        dev_addr,
+        reg,
+        value,
+    })
+}
+
+fn init_exec<B: I2cBackend>(i2c: &I2cHandle<B>, init: &InitCmd) -> Result<(), AfbError> {
+    i2c_write_data(i2c, init.dev_addr, init.reg, &init.value)
+}
+
+// report bus state to binder log and re-initialize devices once bus is reopened
+fn state_to_afb<B: I2cBackend>(inits: Vec<InitCmd>) -> I2cStateHook<B> {
+    Box::new(move |i2c: &I2cHandle<B>, state: I2cBusState| match state {
+        I2cBusState::DEGRADED => {
+            afb_log_msg!(Warning, None, "i2cbus:{} degraded, waiting for reopen", i2c.get_name());
         }
-        2 => {
-            i2c.write(dev_addr, cmd_reg, hexa_string_to_u16(cmd_value)?)?;
+        I2cBusState::RECOVERED => {
+            afb_log_msg!(Notice, None, "i2cbus:{} recovered, replaying {} init command(s)", i2c.get_name(), inits.len());
+            for init in &inits {
+                if let Err(error) = init_exec(i2c, init) {
+                    afb_log_msg!(Error, None, "device:{} init replay fail:{}", init.dev_uid, error);
+                }
+            }
         }
+        I2cBusState::READY => {}
+    })
+}
+
+// SMBus primitive behind a command, DATA is the register oriented byte/word/block access
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CmdOp {
+    DATA,
+    RECEIVE_BYTE,
+    SEND_BYTE,
+    QUICK,
+    PROCESS_CALL,
+    BLOCK_PROCESS_CALL,
+}
+
+fn parse_cmd_op(input: String) -> Result<CmdOp, AfbError> {
+    let op = match input.to_lowercase().as_str() {
+        "data" => CmdOp::DATA,
+        "receive-byte" => CmdOp::RECEIVE_BYTE,
+        "send-byte" => CmdOp::SEND_BYTE,
+        "quick" => CmdOp::QUICK,
+        "process-call" => CmdOp::PROCESS_CALL,
+        "block-process-call" => CmdOp::BLOCK_PROCESS_CALL,
         _ => {
             return afb_error!(
-                "i2c-init-size",
-                "invalid size:{} should Byte(1) & World(2)", cmd_size
+                "i2c-config-fail",
+                "invalid op:{} should be data|receive-byte|send-byte|quick|process-call|block-process-call", input
             )
         }
+    };
+    Ok(op)
+}
+
+// reject at config time commands the adapter cannot run
+fn check_cmd_func<B: I2cBackend>(i2c: &I2cHandle<B>, addr: u32, op: CmdOp, size: u8) -> Result<(), AfbError> {
+    match op {
+        CmdOp::DATA if i2c.get_addr_cfg(addr).reg_width == I2cRegWidth::BIT16 => {
+            check_data_size(size)?;
+            i2c.check_func(I2cFunc::I2C)?;
+        }
+        CmdOp::DATA => {
+            check_data_size(size)?;
+            check_data_func(i2c, SmbusRw::READ, size)?;
+            check_data_func(i2c, SmbusRw::WRITE, size)?;
+        }
+        CmdOp::RECEIVE_BYTE => i2c.check_func(I2cFunc::SMBUS_READ_BYTE)?,
+        CmdOp::SEND_BYTE => i2c.check_func(I2cFunc::SMBUS_WRITE_BYTE)?,
+        CmdOp::QUICK => i2c.check_func(I2cFunc::SMBUS_QUICK)?,
+        CmdOp::PROCESS_CALL => i2c.check_func(I2cFunc::SMBUS_PROC_CALL)?,
+        CmdOp::BLOCK_PROCESS_CALL => i2c.check_func(I2cFunc::SMBUS_BLOCK_PROC_CALL)?,
     }
     Ok(())
 }
 
+// DONE replies once the last step is written, IMMEDIATE replies at once and
+// reports completion through the command event
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+enum PresetReply {
+    DONE,
+    IMMEDIATE,
+}
+
 #[derive(Clone)]
 struct PresetData {
     delay: Option<Duration>,
-    values: Vec<u16>,
+    mask: Option<SmbusData>,
+    values: Vec<SmbusData>,
+    reply: PresetReply,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
 enum PresetValue {
     READ,
     WRITE,
+    CALL,
     PRESET(PresetData),
 }
 
@@ -106,101 +395,701 @@ struct PreSetAction {
     value: PresetValue,
 }
 
-struct RqtI2ccCtx {
-    i2c: Rc<I2cHandle>,
-    actions: Vec<PreSetAction>,
+// posted job argument, 'token' identifies the parked binder continuation
+struct BusCompletion {
+    token: u64,
+}
+
+type BinderDone = Box<dyn FnOnce(Result<(), AfbError>)>;
+
+struct BusParked {
+    next: u64,
+    done: HashMap<u64, BinderDone>,
+}
+
+struct BusDoneCtx {
+    parked: &'static Mutex<BusParked>,
+}
+
+// afb_sched_post_job only queues the job under the libafb scheduler lock, posting is the
+// one afb call made from the i2c-io thread, requests and events never leave binder threads
+#[derive(Clone, Copy)]
+struct BinderJob(&'static AfbSchedJob);
+unsafe impl Send for BinderJob {}
+unsafe impl Sync for BinderJob {}
+
+impl BinderJob {
+    fn post(&self, delay: Duration, token: u64) -> Result<(), AfbError> {
+        self.0.post(delay.as_millis() as i64, BusCompletion { token })?;
+        Ok(())
+    }
+}
+
+// binder side of a bus worker: continuations are parked here and run from the bus job
+// callback once their I/O completed, the i2c-io thread only sees their token
+#[derive(Clone, Copy)]
+struct BusBinder {
+    parked: &'static Mutex<BusParked>,
+    job: BinderJob,
+}
+
+fn bus_done_cb(
+    _job: &AfbSchedJob,
+    signal: i32,
+    args: &AfbCtxData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<BusDoneCtx>()?;
+    let completion = args.get_ref::<BusCompletion>()?;
+    let done = ctx
+        .parked
+        .lock()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .done
+        .remove(&completion.token);
+    if let Some(done) = done {
+        match signal {
+            0 => done(Ok(())),
+            _ => done(afb_error!("i2c-bus-job", "completion job interrupted by signal:{}", signal)),
+        }
+    }
+    Ok(())
+}
+
+impl BusBinder {
+    fn new(uid: &'static str) -> Result<BusBinder, AfbError> {
+        let parked: &'static Mutex<BusParked> = Box::leak(Box::new(Mutex::new(BusParked {
+            next: 0,
+            done: HashMap::new(),
+        })));
+        let job = AfbSchedJob::new(uid)
+            .set_info("i2c bus job completion")
+            .set_callback(bus_done_cb)
+            .set_context(BusDoneCtx { parked })
+            .finalize()?;
+        Ok(BusBinder {
+            parked,
+            job: BinderJob(job),
+        })
+    }
+
+    fn park(&self, done: BinderDone) -> u64 {
+        let mut parked = self.parked.lock().unwrap_or_else(|poison| poison.into_inner());
+        parked.next += 1;
+        let token = parked.next;
+        parked.done.insert(token, done);
+        token
+    }
+
+    // run 'done' on a binder thread once 'delay' elapsed, the job is created once per bus
+    // and only re-posted, nothing is left to release when the delay ends
+    fn post_after<F>(&self, delay: Duration, done: F)
+    where
+        F: FnOnce(Result<(), AfbError>) + 'static,
+    {
+        let token = self.park(Box::new(done));
+        if let Err(error) = self.job.post(delay, token) {
+            let done = self
+                .parked
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .done
+                .remove(&token);
+            if let Some(done) = done {
+                done(Err(error));
+            }
+        }
+    }
+
+    // queue 'job' on the bus worker (FIFO), 'done' gets its result back on a binder thread
+    fn queue<B, T, J, F>(&self, bus: &I2cAsync<B>, job: J, done: F)
+    where
+        B: I2cBackend + 'static,
+        T: Send + 'static,
+        J: FnOnce(&I2cHandle<B>) -> Result<T, AfbError> + Send + 'static,
+        F: FnOnce(Result<T, AfbError>) + 'static,
+    {
+        let slot = Arc::new(Mutex::new(None));
+        let result = slot.clone();
+        let token = self.park(Box::new(move |posted: Result<(), AfbError>| {
+            let result = result.lock().unwrap_or_else(|poison| poison.into_inner()).take();
+            done(posted.and_then(|()| match result {
+                Some(result) => result,
+                None => afb_error!("i2c-bus-job", "bus job completed without result"),
+            }))
+        }));
+
+        let binder = self.job;
+        bus.run(move |i2c| Ok(job(i2c))).on_complete(move |result| {
+            *slot.lock().unwrap_or_else(|poison| poison.into_inner()) =
+                Some(result.unwrap_or_else(|error| Err(AfbError::from(error))));
+            if let Err(error) = binder.post(Duration::ZERO, token) {
+                afb_log_msg!(Critical, None, "bus job completion:{} lost {}", token, error);
+            }
+        });
+    }
+}
+
+// queue a job on the bus worker, the job returns plain data and the request is replied
+// from a binder thread with 'reply' once the job completed, or with the job error
+fn rqt_queue<B, T, J, R>(rqt: &AfbRequest, bus: &I2cAsync<B>, binder: &BusBinder, job: J, reply: R)
+where
+    B: I2cBackend + 'static,
+    T: Send + 'static,
+    J: FnOnce(&I2cHandle<B>) -> Result<T, AfbError> + Send + 'static,
+    R: FnOnce(&AfbRequest, T) -> Result<(), AfbError> + 'static,
+{
+    let rqt = rqt.add_ref();
+    binder.queue(bus, job, move |result| {
+        if let Err(error) = result.and_then(|value| reply(&rqt, value)) {
+            rqt.reply(format!("{}", error), -1);
+        }
+    });
+}
+
+#[derive(Clone, Copy)]
+struct I2cCmd {
     dev_addr: u32,
-    cmd_reg: u8,
+    cmd_op: CmdOp,
+    cmd_reg: u16,
     cmd_size: u8,
+    cmd_type: Option<I2cDataType>,
+    endian: I2cEndian,
+}
+
+struct RqtI2ccCtx<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+    binder: BusBinder,
+    actions: Vec<PreSetAction>,
+    cmd: I2cCmd,
+    event: Option<&'static AfbEvent>,
+    running: Arc<Mutex<bool>>,
+}
+
+// request decoded on the binder thread, jsonc query never reaches the bus worker
+#[allow(clippy::upper_case_acronyms)]
+enum CmdJob {
+    READ,
+    WRITE(Option<SmbusData>, SmbusData),
+    CALL(String),
+}
+
+// write path shared by 'set' and presets
+fn cmd_write<B: I2cBackend>(
+    i2c: &I2cHandle<B>,
+    cmd: &I2cCmd,
+    mask: Option<&SmbusData>,
+    data: &SmbusData,
+) -> Result<(), AfbError> {
+    match (cmd.cmd_op, data) {
+        (CmdOp::DATA, _) => match mask {
+            Some(mask) => i2c_update_data(i2c, cmd.dev_addr, cmd.cmd_reg, mask, data)?,
+            None => i2c_write_data(i2c, cmd.dev_addr, cmd.cmd_reg, data)?,
+        },
+        (op, _) if mask.is_some() => return afb_error!("i2c-cmd-op", "op:{:?} does not support mask", op),
+        (CmdOp::SEND_BYTE, SmbusData::BYTE(value)) => i2c.send_byte(cmd.dev_addr, *value)?,
+        (CmdOp::QUICK, _) => i2c.quick(cmd.dev_addr, SmbusRw::WRITE)?,
+        (op, _) => return afb_error!("i2c-cmd-op", "op:{:?} does not support write", op),
+    }
+    Ok(())
+}
+
+// bus worker answer, converted to json by cmd_reply on the binder thread
+#[allow(clippy::upper_case_acronyms)]
+enum CmdReply {
+    NONE,
+    UNSIGNED(u32),
+    SIGNED(i32),
+    BYTES(Vec<u8>),
+}
+
+fn cmd_reply(rqt: &AfbRequest, reply: CmdReply) -> Result<(), AfbError> {
+    match reply {
+        CmdReply::NONE => rqt.reply(AFB_NO_DATA, 0),
+        CmdReply::UNSIGNED(value) => rqt.reply(value, 0),
+        CmdReply::SIGNED(value) => rqt.reply(value, 0),
+        CmdReply::BYTES(data) => {
+            let jreply = JsoncObj::array();
+            for value in data {
+                jreply.append(value as u32)?;
+            }
+            rqt.reply(jreply, 0);
+        }
+    }
+    Ok(())
+}
+
+// runs on the bus worker, the request itself stays on the binder thread
+fn cmd_exec<B: I2cBackend>(i2c: &I2cHandle<B>, cmd: &I2cCmd, job: CmdJob) -> Result<CmdReply, AfbError> {
+    let reply = match job {
+        CmdJob::READ => match (cmd.cmd_op, cmd.cmd_type) {
+            (CmdOp::RECEIVE_BYTE, _) => CmdReply::UNSIGNED(i2c.receive_byte(cmd.dev_addr)? as u32),
+            (CmdOp::QUICK, _) => {
+                i2c.quick(cmd.dev_addr, SmbusRw::READ)?;
+                CmdReply::NONE
+            }
+            (CmdOp::DATA, Some(dtype)) => {
+                let data = i2c_read_data(i2c, cmd.dev_addr, cmd.cmd_reg, cmd.cmd_size)?;
+                let value = dtype.decode(&data, cmd.endian)?;
+                if dtype.is_signed() {
+                    CmdReply::SIGNED(value as i32)
+                } else {
+                    CmdReply::UNSIGNED(value as u32)
+                }
+            }
+            (CmdOp::DATA, None) => match i2c_read_data(i2c, cmd.dev_addr, cmd.cmd_reg, cmd.cmd_size)? {
+                SmbusData::BYTE(data) => CmdReply::UNSIGNED(data as u32),
+                SmbusData::WORD(data) => CmdReply::UNSIGNED(data as u32),
+                SmbusData::BLOCK(data) => CmdReply::BYTES(data),
+                SmbusData::NONE => CmdReply::NONE,
+            },
+            (op, _) => return afb_error!("i2c-cmd-op", "op:{:?} does not support read", op),
+        },
+        CmdJob::WRITE(mask, data) => {
+            cmd_write(i2c, cmd, mask.as_ref(), &data)?;
+            CmdReply::NONE
+        }
+        // process calls write 'value' and reply with device answer
+        CmdJob::CALL(query) => {
+            if cmd.cmd_op == CmdOp::PROCESS_CALL {
+                let value = hexa_string_to_u16(query)?;
+                CmdReply::UNSIGNED(i2c.process_call(cmd.dev_addr, cmd.cmd_reg as u8, value)? as u32)
+            } else {
+                let values = hexa_string_to_bytes(query)?;
+                CmdReply::BYTES(i2c.block_process_call(cmd.dev_addr, cmd.cmd_reg as u8, &values)?)
+            }
+        }
+    };
+    Ok(reply)
+}
+
+// one preset sequence, owned by binder side continuations: each step is queued on the
+// bus worker and the next one posted from the binder with the bus job once the write
+// completed, so the gap between writes is never shorter than 'delay' and the binder
+// never waits
+struct PresetRun<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+    binder: BusBinder,
+    cmd: I2cCmd,
+    action: String,
+    data: PresetData,
+    next: usize,
+    running: Arc<Mutex<bool>>,
+    reply: Option<AfbRequest>,
+    event: Option<&'static AfbEvent>,
+}
+
+impl<B: I2cBackend + 'static> PresetRun<B> {
+    fn step(mut self) {
+        let cmd = self.cmd;
+        let mask = self.data.mask.clone();
+        let value = self.data.values[self.next].clone();
+        self.next += 1;
+
+        let bus = self.bus.clone();
+        let binder = self.binder;
+        binder.queue(
+            &bus,
+            move |i2c| cmd_write(i2c, &cmd, mask.as_ref(), &value),
+            move |result| self.stepped(result),
+        );
+    }
+
+    // remaining steps are dropped once a step failed
+    fn stepped(self, result: Result<(), AfbError>) {
+        match (result, self.data.delay) {
+            (Err(error), _) => self.done(Err(error)),
+            (Ok(()), _) if self.next == self.data.values.len() => self.done(Ok(())),
+            (Ok(()), None) => self.step(),
+            // deadline of next step counts from previous write completion
+            (Ok(()), Some(delay)) => {
+                let binder = self.binder;
+                binder.post_after(delay, move |result| match result {
+                    Ok(()) => self.step(),
+                    Err(error) => self.done(Err(error)),
+                });
+            }
+        }
+    }
+
+    // command is released before replying, client may chain next preset from the reply
+    fn done(self, result: Result<(), AfbError>) {
+        *self.running.lock().unwrap_or_else(|poison| poison.into_inner()) = false;
+        if let Some(rqt) = &self.reply {
+            match &result {
+                Ok(()) => rqt.reply(AFB_NO_DATA, 0),
+                Err(error) => rqt.reply(format!("{}", error), -1),
+            }
+        }
+        if let Some(event) = self.event {
+            if let Err(error) = self.notify(event, &result) {
+                afb_log_msg!(Error, None, "preset:{} event push failed {}", self.action, error);
+            }
+        }
+    }
+
+    // {'action':'lock','status':'done|error','info':'...'}
+    fn notify(&self, event: &AfbEvent, result: &Result<(), AfbError>) -> Result<(), AfbError> {
+        let jevent = JsoncObj::new();
+        jevent.add("action", self.action.as_str())?;
+        match result {
+            Ok(()) => jevent.add("status", "done")?,
+            Err(error) => {
+                jevent.add("status", "error")?;
+                jevent.add("info", format!("{}", error))?
+            }
+        };
+        event.push(jevent);
+        Ok(())
+    }
+}
+
+// sequences on one command never interleave (eg: lock 'on' and 'off' driving the
+// same bits), a preset or write received while one runs is rejected
+fn preset_busy(action: &str) -> Result<(), AfbError> {
+    afb_error!(
+        "i2c-preset-busy",
+        "action:{} rejected, a preset sequence is running on this command", action
+    )
 }
 
-fn rqt_i2c_cb(rqt: &AfbRequest, args: &AfbRqtData, ctx_data: &AfbCtxData) -> Result<(), AfbError> {
-    let ctx = ctx_data.get_ref::<RqtI2ccCtx>()?;
+// first step is queued now, following ones once their delay elapsed
+fn preset_start<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    ctx: &RqtI2ccCtx<B>,
+    action: &str,
+    data: &PresetData,
+) -> Result<(), AfbError> {
+    // taken under the command lock writes are queued with (see rqt_i2c_cb)
+    let event = {
+        let mut running = ctx.running.lock().unwrap_or_else(|poison| poison.into_inner());
+        if *running {
+            return preset_busy(action);
+        }
+        let event = match (data.reply, ctx.event) {
+            (PresetReply::IMMEDIATE, Some(event)) => {
+                event.subscribe(rqt)?;
+                Some(event)
+            }
+            _ => None,
+        };
+        *running = true;
+        event
+    };
+
+    let reply = match event {
+        Some(_) => {
+            rqt.reply(AFB_NO_DATA, 0);
+            None
+        }
+        None => Some(rqt.add_ref()),
+    };
+
+    let run = PresetRun {
+        bus: ctx.bus.clone(),
+        binder: ctx.binder,
+        cmd: ctx.cmd,
+        action: action.to_string(),
+        data: data.clone(),
+        next: 0,
+        running: ctx.running.clone(),
+        reply,
+        event,
+    };
+
+    // nothing to write, complete right away
+    if data.values.is_empty() {
+        run.done(Ok(()));
+    } else {
+        run.step();
+    }
+    Ok(())
+}
+
+fn rqt_i2c_cb<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    args: &AfbRqtData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<RqtI2ccCtx<B>>()?;
     let query = args.get::<JsoncObj>(0)?;
     let action = query.get::<String>("action")?.to_lowercase();
+    let cmd = ctx.cmd;
 
     for preset in &ctx.actions {
         if action == preset.action {
-            match &preset.value {
-                PresetValue::READ => match ctx.cmd_size {
-                    1 => {
-                        let data: u8 = ctx.i2c.read(ctx.dev_addr, ctx.cmd_reg)?;
-                        rqt.reply(data as u32, 0);
-                    }
-                    2 => {
-                        let data: u16 = ctx.i2c.read(ctx.dev_addr, ctx.cmd_reg)?;
-                        rqt.reply(data as u32, 0);
-                    }
-                    _ => {
-                        return afb_error!(
-                            "rqt-i2c-size",
-                           "invalid size:{} should Byte(1) & World(2)", ctx.cmd_size
-                        )
-                    }
-                },
+            let job = match &preset.value {
+                PresetValue::READ => CmdJob::READ,
                 PresetValue::WRITE => {
-                    let query = query.get::<String>("value")?;
-                    match ctx.cmd_size {
-                        1 => {
-                            ctx.i2c
-                                .write(ctx.dev_addr, ctx.cmd_reg, hexa_string_to_u8(query)?)?;
-                            rqt.reply(AFB_NO_DATA, 0);
-                        }
-                        2 => {
-                            ctx.i2c
-                                .write(ctx.dev_addr, ctx.cmd_reg, hexa_string_to_u16(query)?)?;
-                            rqt.reply(AFB_NO_DATA, 0);
-                        }
-                        _ => {
-                            return afb_error!(
-                                "rqt-i2c-size",
-                                "invalid size:{} should Byte(1) & World(2)", ctx.cmd_size
-                            )
-                        }
-                    }
+                    let data = match (cmd.cmd_op, cmd.cmd_type) {
+                        (CmdOp::QUICK, _) => SmbusData::NONE,
+                        (_, Some(dtype)) => json_to_data(
+                            query.get::<i64>("value"),
+                            query.get::<String>("value"),
+                            dtype,
+                            cmd.endian,
+                        )?,
+                        (_, None) => hexa_string_to_data(query.get::<String>("value")?, cmd.cmd_size)?,
+                    };
+                    let mask = match query.get::<String>("mask") {
+                        Ok(value) => Some(mask_to_data(value, cmd.cmd_type, cmd.endian, cmd.cmd_size)?),
+                        Err(_) => None,
+                    };
+                    CmdJob::WRITE(mask, data)
                 }
-                // loop on preset value if needed wait except for last preset
-                PresetValue::PRESET(data) => match ctx.cmd_size {
-                    1 => {
-                        let count = data.values.len();
-                        for idx in 0..count {
-                            ctx.i2c
-                                .write(ctx.dev_addr, ctx.cmd_reg, data.values[idx] as u8)?;
-                            if let Some(value) = data.delay {
-                                if idx < count - 1 {
-                                    thread::sleep(value)
-                                }
-                            }
-                        }
-                        rqt.reply(AFB_NO_DATA, 0);
-                    }
-                    2 => {
-                        let count = data.values.len();
-                        for idx in 0..count {
-                            ctx.i2c
-                                .write(ctx.dev_addr, ctx.cmd_reg, data.values[idx] as u16)?;
-                            if let Some(value) = data.delay {
-                                if idx < count - 1 {
-                                    thread::sleep(value)
-                                }
-                            }
-                        }
-                        rqt.reply(AFB_NO_DATA, 0);
-                    }
-                    _ => {
+                PresetValue::CALL => CmdJob::CALL(query.get::<String>("value")?),
+                PresetValue::PRESET(data) => {
+                    preset_start(rqt, ctx, &action, data)?;
+                    continue;
+                }
+            };
+
+            // writes are queued under the command lock, a preset cannot start in between
+            let running = ctx.running.lock().unwrap_or_else(|poison| poison.into_inner());
+            if *running && matches!(job, CmdJob::WRITE(..)) {
+                return preset_busy(&action);
+            }
+            rqt_queue(rqt, &ctx.bus, &ctx.binder, move |i2c| cmd_exec(i2c, &cmd, job), cmd_reply);
+        }
+    }
+    Ok(())
+}
+
+struct RqtRawCtx<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+    binder: BusBinder,
+}
+
+// raw combined transaction {'msgs':[{'addr':'0x50','write':'0x0010'},{'addr':'0x50','read':4}]}
+fn rqt_raw_cb<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    args: &AfbRqtData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<RqtRawCtx<B>>()?;
+    let query = args.get::<JsoncObj>(0)?;
+    let jmsgs = query.get::<JsoncObj>("msgs")?;
+
+    let mut msgs = Vec::new();
+    for idx in 0..jmsgs.count()? {
+        let jmsg = jmsgs.index::<JsoncObj>(idx)?;
+        let addr = hexa_string_to_u16(jmsg.get::<String>("addr")?)?;
+        let mut msg = if let Ok(len) = jmsg.get::<u32>("read") {
+            I2cMsg::read(addr, len as usize)
+        } else {
+            I2cMsg::write(addr, &hexa_string_to_bytes(jmsg.get::<String>("write")?)?)
+        };
+
+        if let Ok(flags) = jmsg.get::<JsoncObj>("flags") {
+            for jdx in 0..flags.count()? {
+                msg = match flags.index::<String>(jdx)?.to_lowercase().as_str() {
+                    "no-start" => msg.no_start(),
+                    "ignore-nak" => msg.ignore_nak(),
+                    "ten-bit" => msg.ten_bit(),
+                    flag => {
                         return afb_error!(
-                            "rqt-i2c-size",
-                            "invalid size:{} should Byte(1) & World(2)", ctx.cmd_size
+                            "rqt-raw-flag",
+                            "invalid flag:{} should no-start|ignore-nak|ten-bit", flag
                         )
                     }
-                },
+                };
             }
         }
+        msgs.push(msg);
     }
+
+    // one byte array per read segment
+    rqt_queue(
+        rqt,
+        &ctx.bus,
+        &ctx.binder,
+        move |i2c| Ok(i2c.transfer(&msgs)?),
+        |rqt, segments| {
+            let jreply = JsoncObj::array();
+            for data in segments {
+                let jdata = JsoncObj::array();
+                for value in data {
+                    jdata.append(value as u32)?;
+                }
+                jreply.append(jdata)?;
+            }
+            rqt.reply(jreply, 0);
+            Ok(())
+        },
+    );
+    Ok(())
+}
+
+struct RqtScanCtx<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+    binder: BusBinder,
+}
+
+// i2cdetect like probe {'first':'0x08','last':'0x77','mode':'auto|quick|read'}
+fn rqt_scan_cb<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    args: &AfbRqtData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<RqtScanCtx<B>>()?;
+    let query = args.get::<JsoncObj>(0)?;
+
+    let first = match query.get::<String>("first") {
+        Ok(value) => hexa_string_to_u32(value)?,
+        Err(_) => 0x08,
+    };
+    let last = match query.get::<String>("last") {
+        Ok(value) => hexa_string_to_u32(value)?,
+        Err(_) => 0x77,
+    };
+    let mode = match query.get::<String>("mode") {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "auto" => I2cScanMode::AUTO,
+            "quick" => I2cScanMode::QUICK,
+            "read" => I2cScanMode::READ,
+            _ => {
+                return afb_error!(
+                    "rqt-scan-mode",
+                    "invalid mode:{} should be auto|quick|read", value
+                )
+            }
+        },
+        Err(_) => I2cScanMode::AUTO,
+    };
+
+    rqt_queue(
+        rqt,
+        &ctx.bus,
+        &ctx.binder,
+        move |i2c| Ok(i2c.scan(first, last, mode)?),
+        |rqt, result| {
+            let jfound = JsoncObj::array();
+            for addr in result.found {
+                jfound.append(format!("{:#04x}", addr))?;
+            }
+            let jbusy = JsoncObj::array();
+            for addr in result.busy {
+                jbusy.append(format!("{:#04x}", addr))?;
+            }
+            let jreply = JsoncObj::new();
+            jreply.add("found", jfound)?;
+            jreply.add("busy", jbusy)?;
+            rqt.reply(jreply, 0);
+            Ok(())
+        },
+    );
     Ok(())
 }
 
-pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(), AfbError> {
+struct RqtDumpCtx<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+    binder: BusBinder,
+    dev_addr: u32,
+}
+
+// i2cdump like snapshot {'first':'0x00','last':'0xFF','mode':'byte|word|block'}
+// reply {'0x00':value,...} unreadable registers (i2cdump 'XX') are omitted
+fn rqt_dump_cb<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    args: &AfbRqtData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<RqtDumpCtx<B>>()?;
+    let query = args.get::<JsoncObj>(0)?;
+
+    let first = match query.get::<String>("first") {
+        Ok(value) => hexa_string_to_u8(value)?,
+        Err(_) => 0x00,
+    };
+    let last = match query.get::<String>("last") {
+        Ok(value) => hexa_string_to_u8(value)?,
+        Err(_) => 0xFF,
+    };
+    if first > last {
+        return afb_error!(
+            "rqt-dump-range",
+            "invalid range first:{:#04x} > last:{:#04x}", first, last
+        );
+    }
+    let mode = match query.get::<String>("mode") {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "byte" => I2cDumpMode::BYTE,
+            "word" => I2cDumpMode::WORD,
+            "block" => I2cDumpMode::BLOCK,
+            _ => {
+                return afb_error!(
+                    "rqt-dump-mode",
+                    "invalid mode:{} should be byte|word|block", value
+                )
+            }
+        },
+        Err(_) => I2cDumpMode::BYTE,
+    };
+
+    let dev_addr = ctx.dev_addr;
+    rqt_queue(
+        rqt,
+        &ctx.bus,
+        &ctx.binder,
+        move |i2c| Ok(i2c.dump(dev_addr, first..=last, mode)?),
+        |rqt, registers| {
+            let jreply = JsoncObj::new();
+            for (reg, data) in registers {
+                let key = format!("{:#04x}", reg);
+                match data {
+                    Some(SmbusData::BYTE(value)) => jreply.add(&key, value as u32)?,
+                    Some(SmbusData::WORD(value)) => jreply.add(&key, value as u32)?,
+                    _ => continue,
+                };
+            }
+            rqt.reply(jreply, 0);
+            Ok(())
+        },
+    );
+    Ok(())
+}
+
+struct RqtStatusCtx<B: I2cBackend + 'static> {
+    bus: Arc<I2cAsync<B>>,
+}
+
+// answered on binder thread, queue metrics must stay readable while the bus worker is busy
+fn rqt_status_cb<B: I2cBackend + 'static>(
+    rqt: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx_data: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx_data.get_ref::<RqtStatusCtx<B>>()?;
+    let stats = ctx.bus.get_stats();
+
+    let jreply = JsoncObj::new();
+    jreply.add("bus", ctx.bus.get_handle().get_name())?;
+    jreply.add("state", format!("{:?}", ctx.bus.get_handle().get_state()).to_lowercase())?;
+    jreply.add("pending", stats.pending as u32)?;
+    jreply.add("completed", stats.completed)?;
+    jreply.add("wait-last-ms", stats.last_wait.as_secs_f64() * 1000.0)?;
+    jreply.add("wait-max-ms", stats.max_wait.as_secs_f64() * 1000.0)?;
+    jreply.add("wait-avg-ms", stats.get_avg_wait().as_secs_f64() * 1000.0)?;
+    rqt.reply(jreply, 0);
+    Ok(())
+}
+
+// route libi2c transaction events to binder log
+fn trace_to_afb(level: TraceLevel) -> Box<dyn I2cTraceSink> {
+    Box::new(move |event: &I2cTraceEvent| match level {
+        TraceLevel::ERROR => afb_log_msg!(Error, None, "{}", event),
+        TraceLevel::WARNING => afb_log_msg!(Warning, None, "{}", event),
+        TraceLevel::NOTICE => afb_log_msg!(Notice, None, "{}", event),
+        TraceLevel::INFO => afb_log_msg!(Info, None, "{}", event),
+        TraceLevel::DEBUG => afb_log_msg!(Debug, None, "{}", event),
+    })
+}
+
+pub(crate) fn register_verbs<B: I2cBackend + 'static>(
+    api: &mut AfbApi,
+    config: BindingCfg,
+    i2c: Arc<I2cHandle<B>>,
+) -> Result<(), AfbError> {
     // default actions
     let get = PreSetAction {
         action: "set".to_string(),
@@ -211,8 +1100,56 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
         value: PresetValue::READ,
     };
 
-    // open i2c bus and send init commands if needed
-    let i2c = Rc::new(I2cHandle::new(config.i2cbus)?);
+    if let Some(timeout) = config.timeout {
+        i2c.set_timeout(timeout)?;
+    }
+    if let Some(retries) = config.retries {
+        i2c.set_retries(retries)?;
+    }
+    if let Some(level) = config.trace {
+        i2c.set_tracer(Some(trace_to_afb(level)));
+    }
+    i2c.set_recovery(config.recovery);
+    let mut dev_inits = Vec::new();
+
+    // every verb queues its I/O on this bus worker, device init above runs inline at config time
+    let bus = Arc::new(I2cAsync::new(i2c.clone())?);
+    let binder = BusBinder::new(to_static_str(format!("i2c-io:{}", i2c.get_name())))?;
+
+    let funcs: Vec<&str> = i2c.get_funcs().get_list().iter().map(|func| func.as_str()).collect();
+    afb_log_msg!(Debug, None, "i2cbus:{} funcs:{:?}", i2c.get_name(), funcs);
+
+    // generic raw verb for devices without config (repeated start, 16bit pointers, ...)
+    // reaches any address and bypasses per command ACLs, restricted to admin
+    let verb = AfbVerb::new("transfer");
+    verb.set_info("raw i2c combined transaction")
+        .set_usage("{'msgs':[{'addr':'0x??','write':'0x????'},{'addr':'0x??','read':len,'flags':['no-start|ignore-nak|ten-bit']}]}")
+        .set_permission(config.admin)
+        .set_callback(rqt_raw_cb::<B>)
+        .set_context(RqtRawCtx {
+            bus: bus.clone(),
+            binder,
+        });
+    api.add_verb(verb.finalize()?);
+
+    // bus probing may trigger side effects on some chips, restricted to admin
+    let verb = AfbVerb::new("scan");
+    verb.set_info("probe bus addresses (i2cdetect)")
+        .set_usage("{'first':'0x08','last':'0x77','mode':'auto|quick|read'}")
+        .set_permission(config.admin)
+        .set_callback(rqt_scan_cb::<B>)
+        .set_context(RqtScanCtx {
+            bus: bus.clone(),
+            binder,
+        });
+    api.add_verb(verb.finalize()?);
+
+    let verb = AfbVerb::new("status");
+    verb.set_info("bus state and worker queue depth/wait time")
+        .set_usage("no input")
+        .set_callback(rqt_status_cb::<B>)
+        .set_context(RqtStatusCtx { bus: bus.clone() });
+    api.add_verb(verb.finalize()?);
 
     // loop on command and create corresponding verbs
     for idx in 0..config.devices.count()? {
@@ -250,13 +1187,73 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
             None
         };
 
+        // default byte order for typed commands
+        let dev_endian = match device.get::<String>("endian") {
+            Ok(value) => parse_endian(value)?,
+            Err(_) => I2cEndian::LITTLE,
+        };
+
+        if let Ok(value) = device.get::<String>("addr-mode") {
+            let mode = match value.to_lowercase().as_str() {
+                "7bit" => I2cAddrMode::BIT7,
+                "10bit" => I2cAddrMode::BIT10,
+                _ => {
+                    return afb_error!(
+                        "i2c-config-fail",
+                        "device:{} invalid addr-mode:{} should be 7bit|10bit", dev_uid, value
+                    )
+                }
+            };
+            i2c.set_addr_mode(dev_addr, mode)?;
+        }
+
+        // register pointer width, 16bit pointers are big-endian unless reg-endian says otherwise
+        if let Ok(value) = device.get::<u32>("reg-width") {
+            let width = match value {
+                8 => I2cRegWidth::BIT8,
+                16 => I2cRegWidth::BIT16,
+                _ => {
+                    return afb_error!(
+                        "i2c-config-fail",
+                        "device:{} invalid reg-width:{} should be 8|16", dev_uid, value
+                    )
+                }
+            };
+            let endian = match device.get::<String>("reg-endian") {
+                Ok(value) => parse_endian(value)?,
+                Err(_) => I2cEndian::BIG,
+            };
+            i2c.set_reg_width(dev_addr, width, endian)?;
+        }
+
+        // talk to the device even when a kernel driver is bound to it
+        if let Ok(value) = device.get::<bool>("force") {
+            i2c.set_force(dev_addr, value);
+        }
+
+        if let Ok(value) = device.get::<bool>("allow-reserved") {
+            i2c.set_allow_reserved(dev_addr, value);
+        }
+        i2c.check_addr(dev_addr)?;
+
+        // optional SMBus packet error checking, toggled around each transaction
+        if let Ok(value) = device.get::<bool>("pec") {
+            i2c.set_pec(dev_addr, value)?;
+        }
+
         // check device need to be initialized
         if let Ok(inits) = device.get::<JsoncObj>("init") {
             match inits.get_type() {
                 Jtype::Array => {
+                    if i2c.get_addr_cfg(dev_addr).reg_width == I2cRegWidth::BIT16 {
+                        i2c.check_func(I2cFunc::I2C)?;
+                    } else {
+                        check_data_func(&i2c, SmbusRw::WRITE, dev_size)?;
+                    }
                     for kdx in 0..inits.count()? {
-                        let init = inits.index::<JsoncObj>(kdx)?;
-                        cmd_exec(i2c.clone(), dev_addr, dev_size, init.clone())?;
+                        let init = init_parse(&i2c, dev_uid, dev_addr, dev_size, inits.index::<JsoncObj>(kdx)?)?;
+                        init_exec(&i2c, &init)?;
+                        dev_inits.push(init);
                     }
                 }
                 _ => {
@@ -291,17 +1288,58 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
             let cmd_uid = to_static_str(cmd.get::<String>("uid")?);
             let verb = AfbVerb::new(cmd_uid);
 
-            let cmd_reg = hexa_string_to_u8(cmd.get::<String>("register")?)?;
+            let cmd_op = match cmd.get::<String>("op") {
+                Ok(value) => parse_cmd_op(value)?,
+                Err(_) => CmdOp::DATA,
+            };
+
+            // byte and quick primitives do not address any register, process calls
+            // use SMBus 8bit command, data register follows device reg-width
+            let cmd_reg = match cmd_op {
+                CmdOp::DATA => {
+                    let reg = hexa_string_to_u16(cmd.get::<String>("register")?)?;
+                    i2c.check_reg(dev_addr, reg)?;
+                    reg
+                }
+                CmdOp::PROCESS_CALL | CmdOp::BLOCK_PROCESS_CALL => {
+                    hexa_string_to_u8(cmd.get::<String>("register")?)? as u16
+                }
+                _ => 0,
+            };
 
             if let Ok(value) = cmd.get::<String>("info") {
                 verb.set_info(to_static_str(value));
             };
 
-            let cmd_size = if let Ok(value) = cmd.get::<u32>("size") {
-                value as u8
-            } else {
-                dev_size
+            // 'type' defines register width and sign, 'size' remains raw unsigned data
+            let cmd_type = match cmd.get::<String>("type") {
+                Ok(value) => Some(parse_data_type(value)?),
+                Err(_) => None,
             };
+            let endian = match cmd.get::<String>("endian") {
+                Ok(value) => parse_endian(value)?,
+                Err(_) => dev_endian,
+            };
+            if cmd_type.is_some() && cmd_op != CmdOp::DATA {
+                return afb_error!(
+                    "i2c-config-fail",
+                    "cmd:{} 'type' requires op:data not op:{:?}", cmd_uid, cmd_op
+                );
+            }
+            let cmd_size = match (cmd_type, cmd.get::<u32>("size")) {
+                _ if cmd_op == CmdOp::PROCESS_CALL => 2,
+                _ if cmd_op != CmdOp::DATA => 1,
+                (Some(dtype), Ok(value)) if value as u8 != dtype.get_size() => {
+                    return afb_error!(
+                        "i2c-config-fail",
+                        "cmd:{} size:{} does not match type:{:?}", cmd_uid, value, dtype
+                    )
+                }
+                (Some(dtype), _) => dtype.get_size(),
+                (None, Ok(value)) => value as u8,
+                (None, Err(_)) => dev_size,
+            };
+            check_cmd_func(&i2c, dev_addr, cmd_op, cmd_size)?;
 
             let cmd_delay = if let Ok(value) = cmd.get::<u64>("delay") {
                 Some(time::Duration::from_millis(value))
@@ -316,17 +1354,56 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
             // provision default actions and then config presets
             let mut actions = Vec::from([set.clone(), get.clone()]);
             let mut actions_info = "['get',".to_string();
-            if let Ok(presets) = cmd.get::<JsoncObj>("presets") {
+            let mut event = None;
+            if matches!(cmd_op, CmdOp::PROCESS_CALL | CmdOp::BLOCK_PROCESS_CALL) {
+                actions = Vec::from([PreSetAction {
+                    action: "call".to_string(),
+                    value: PresetValue::CALL,
+                }]);
+                actions_info = "['call'".to_string();
+                verb.set_usage("{'action':'call', 'value':'0x??'");
+            } else if let Ok(presets) = cmd.get::<JsoncObj>("presets") {
                 for jdx in 0..presets.count()? {
                     let preset = presets.index::<JsoncObj>(jdx)?;
                     let action = preset.get::<String>("action")?.to_lowercase();
+                    let mask = match preset.get::<String>("mask") {
+                        Ok(value) => Some(mask_to_data(value, cmd_type, endian, cmd_size)?),
+                        Err(_) => None,
+                    };
+                    let reply = match preset.get::<String>("reply") {
+                        Ok(value) => match value.to_lowercase().as_str() {
+                            "done" => PresetReply::DONE,
+                            "immediate" => PresetReply::IMMEDIATE,
+                            _ => {
+                                return afb_error!(
+                                    "i2c-config-fail",
+                                    "cmd:{} preset:{} invalid reply:{} should be done|immediate", cmd_uid, action, value
+                                )
+                            }
+                        },
+                        Err(_) => PresetReply::DONE,
+                    };
+                    // one completion event per command, shared by its immediate presets
+                    if reply == PresetReply::IMMEDIATE && event.is_none() {
+                        event = Some(AfbEvent::new(cmd_uid).finalize()?);
+                    }
                     let mut data = PresetData {
                         delay: cmd_delay,
+                        mask,
                         values: Vec::new(),
+                        reply,
                     };
                     let values = preset.get::<JsoncObj>("values")?;
                     for kdx in 0..values.count()? {
-                        let value = hexa_string_to_u16(values.index::<String>(kdx)?)?;
+                        let value = match cmd_type {
+                            Some(dtype) => json_to_data(
+                                values.index::<i64>(kdx),
+                                values.index::<String>(kdx),
+                                dtype,
+                                endian,
+                            )?,
+                            None => hexa_string_to_data(values.index::<String>(kdx)?, cmd_size)?,
+                        };
                         data.values.push(value);
                     }
                     actions_info.push_str(format!("'{}',", &action).as_str());
@@ -337,7 +1414,7 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
                 }
             } else {
                 actions_info.push_str("'set'");
-                verb.set_usage("{'action':'set|get', 'value':'0x??'");
+                verb.set_usage("{'action':'set|get', 'value':'0x??', 'mask':'0x??'");
                 if let Ok(samples) = cmd.get::<JsoncObj>("samples") {
                     for kdx in 0..samples.count()? {
                         let sample = samples.index::<String>(kdx)?;
@@ -351,23 +1428,51 @@ pub(crate) fn register_verbs(api: &mut AfbApi, config: BindingCfg) -> Result<(),
             actions_info.push_str("]"); // close action info json_string array
             verb.set_actions(to_static_str(actions_info))?;
 
-            verb.set_callback(rqt_i2c_cb)
+            verb.set_callback(rqt_i2c_cb::<B>)
             .set_context(RqtI2ccCtx {
-                i2c: i2c.clone(),
+                bus: bus.clone(),
+                binder,
                 actions,
-                dev_addr,
-                cmd_reg,
-                cmd_size: cmd_size,
+                cmd: I2cCmd {
+                    dev_addr,
+                    cmd_op,
+                    cmd_reg,
+                    cmd_size: cmd_size,
+                    cmd_type,
+                    endian,
+                },
+                event,
+                running: Arc::new(Mutex::new(false)),
             });
 
             // add command to current group
             let group = unsafe { &mut *(group as *mut AfbGroup) };
             group.add_verb(verb.finalize()?);
+            if let Some(event) = event {
+                group.add_event(event);
+            }
         }
 
+        // register snapshot of the whole device, reads may clear status registers, restricted to admin
+        let verb = AfbVerb::new("dump");
+        verb.set_info("dump device registers (i2cdump)")
+            .set_usage("{'first':'0x00','last':'0xFF','mode':'byte|word|block'}")
+            .set_permission(config.admin)
+            .set_callback(rqt_dump_cb::<B>)
+            .set_context(RqtDumpCtx {
+                bus: bus.clone(),
+                binder,
+                dev_addr,
+            });
+        let group = unsafe { &mut *(group as *mut AfbGroup) };
+        group.add_verb(verb.finalize()?);
+
         // add command group to api
         let group = unsafe { &mut *(group as *mut AfbGroup) };
         api.add_group(group.finalize()?);
     }
+
+    // hook installed once every device is configured, init replay follows config order
+    i2c.set_state_hook(Some(state_to_afb(dev_inits)));
     Ok(())
 }
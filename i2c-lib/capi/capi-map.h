@@ -1,31 +0,0 @@
-/*
- * Copyright (C) 2015-2022 IoT.bzh Company
- * Author: Fulup Ar Foll <fulup@iot.bzh>
- *
- * Licensed under the Apache License, Version 2.0 (the "License");
- * you may not use this file except in compliance with the License.
- * You may obtain a copy of the License at
- *
- *   http://www.apache.org/licenses/LICENSE-2.0
- *
- * Unless required by applicable law or agreed to in writing, software
- * distributed under the License is distributed on an "AS IS" BASIS,
- * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
- * See the License for the specific language governing permissions and
- * limitations under the License.
- */
-#include <sys/types.h>
-#include <sys/stat.h>
-#include <unistd.h>
-#include <fcntl.h>
-#include <string.h>
-#include <errno.h>
-#include <sys/ioctl.h>
-#include <i2c/smbus.h>
-#include <linux/i2c-dev.h>
-
-const int BUS_I2C_O_RDWR= O_RDWR;
-const ulong BUS_I2C_SLAVE= I2C_SLAVE;
-
-
-
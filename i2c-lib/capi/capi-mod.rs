@@ -13,16 +13,120 @@
  * WITHOUT WARRANTIES OR CONDITIFNS OF ANY KIND, either express or implied.
  * See the License for the specific language governing permissions and
  * limitaTIFns under the License.
+ *
+ * References:
+ *  linux/include/uapi/linux/i2c.h
+ *  linux/include/uapi/linux/i2c-dev.h
  */
 
-use ::std::os::raw;
-use std::ffi::CStr;
+use std::io;
 
-const MAX_ERROR_LEN: usize = 256;
+// kernel i2c-dev ABI, hand written to avoid bindgen/libclang and libi2c at build time
 pub mod cglue {
     #![allow(dead_code)]
-    #![allow(non_upper_case_globals)]
     #![allow(non_camel_case_types)]
-    #![allow(non_snake_case)]
-    include!("_capi-map.rs");
+    use std::os::raw;
+
+    pub use libc::{
+        ioctl, EAGAIN, EBADF, EBADMSG, EBUSY, EINVAL, EIO, ENODEV, ENOTCONN, ENXIO, EOPNOTSUPP,
+        EPROTO, EPROTONOSUPPORT, EREMOTEIO, ETIMEDOUT,
+    };
+
+    // ioctl request type differs between glibc (ulong) and musl (int)
+    #[cfg(target_env = "musl")]
+    pub type ioctl_req = raw::c_int;
+    #[cfg(not(target_env = "musl"))]
+    pub type ioctl_req = raw::c_ulong;
+
+    // linux/i2c-dev.h
+    pub const BUS_I2C_RETRIES: ioctl_req = 0x0701;
+    pub const BUS_I2C_TIMEOUT: ioctl_req = 0x0702;
+    pub const BUS_I2C_SLAVE: ioctl_req = 0x0703;
+    pub const BUS_I2C_TENBIT: ioctl_req = 0x0704;
+    pub const BUS_I2C_FUNCS: ioctl_req = 0x0705;
+    pub const BUS_I2C_SLAVE_FORCE: ioctl_req = 0x0706;
+    pub const BUS_I2C_RDWR: ioctl_req = 0x0707;
+    pub const BUS_I2C_PEC: ioctl_req = 0x0708;
+    pub const BUS_I2C_SMBUS: ioctl_req = 0x0720;
+
+    // linux/i2c.h
+    pub const BUS_I2C_SMBUS_READ: u8 = 1;
+    pub const BUS_I2C_SMBUS_WRITE: u8 = 0;
+    pub const BUS_I2C_SMBUS_QUICK: u32 = 0;
+    pub const BUS_I2C_SMBUS_BYTE: u32 = 1;
+    pub const BUS_I2C_SMBUS_BYTE_DATA: u32 = 2;
+    pub const BUS_I2C_SMBUS_WORD_DATA: u32 = 3;
+    pub const BUS_I2C_SMBUS_PROC_CALL: u32 = 4;
+    pub const BUS_I2C_SMBUS_BLOCK_DATA: u32 = 5;
+    pub const BUS_I2C_SMBUS_I2C_BLOCK_BROKEN: u32 = 6;
+    pub const BUS_I2C_SMBUS_BLOCK_PROC_CALL: u32 = 7;
+    pub const BUS_I2C_SMBUS_I2C_BLOCK_DATA: u32 = 8;
+    pub const BUS_I2C_SMBUS_BLOCK_MAX: usize = 32;
+
+    #[repr(C)]
+    pub struct i2c_msg {
+        pub addr: u16,
+        pub flags: u16,
+        pub len: u16,
+        pub buf: *mut u8,
+    }
+
+    #[repr(C)]
+    pub struct i2c_rdwr_ioctl_data {
+        pub msgs: *mut i2c_msg,
+        pub nmsgs: u32,
+    }
+
+    // block[0] holds length, +1 for user-space compatibility, +1 for PEC
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub union i2c_smbus_data {
+        pub byte: u8,
+        pub word: u16,
+        pub block: [u8; BUS_I2C_SMBUS_BLOCK_MAX + 2],
+    }
+
+    #[repr(C)]
+    pub struct i2c_smbus_ioctl_data {
+        pub read_write: u8,
+        pub command: u8,
+        pub size: u32,
+        pub data: *mut i2c_smbus_data,
+    }
+
+    /// Same contract as i2c-tools libi2c i2c_smbus_access(), errno is left set on failure.
+    ///
+    /// # Safety
+    /// raw_fd should be an open i2c-dev file and data valid for the whole call
+    pub unsafe fn i2c_smbus_access(
+        raw_fd: raw::c_int,
+        read_write: u8,
+        command: u8,
+        size: u32,
+        data: *mut i2c_smbus_data,
+    ) -> raw::c_int {
+        let mut args = i2c_smbus_ioctl_data {
+            read_write,
+            command,
+            size,
+            data,
+        };
+        ioctl(raw_fd, BUS_I2C_SMBUS, &mut args as *mut i2c_smbus_ioctl_data)
+    }
+}
+
+pub fn get_perror() -> String {
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        // strip rust ' (os error N)' suffix to keep strerror wording
+        Some(errno) => error
+            .to_string()
+            .trim_end_matches(&format!(" (os error {})", errno))
+            .to_string(),
+        None => error.to_string(),
+    }
+}
+
+pub fn get_errno() -> i32 {
+    io::Error::last_os_error().raw_os_error().unwrap_or(0)
 }
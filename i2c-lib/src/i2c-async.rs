@@ -0,0 +1,435 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::prelude::*;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type I2cCompletion<R> = Box<dyn FnOnce(Result<R, I2cError>) + Send>;
+type I2cJob<B> = Box<dyn FnOnce(&I2cHandle<B>) + Send>;
+
+struct I2cFutureState<R> {
+    result: Option<Result<R, I2cError>>,
+    waker: Option<Waker>,
+    callback: Option<I2cCompletion<R>>,
+}
+
+struct I2cFutureShared<R> {
+    state: Mutex<I2cFutureState<R>>,
+    done: Condvar,
+}
+
+impl<R> I2cFutureShared<R> {
+    fn lock(&self) -> MutexGuard<'_, I2cFutureState<R>> {
+        self.state.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    // callback takes the result, otherwise it is parked for poll/wait. A panicking
+    // callback is dropped, it must not stop the I/O thread serving later jobs
+    fn complete(&self, result: Result<R, I2cError>) {
+        let mut state = self.lock();
+        if let Some(callback) = state.callback.take() {
+            drop(state);
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| callback(result)));
+            return;
+        }
+        state.result = Some(result);
+        let waker = state.waker.take();
+        drop(state);
+        self.done.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+// completion of a job queued on I2cAsync, runtime agnostic: await it from any executor,
+// block with wait() or get the result from the I/O thread with on_complete()
+pub struct I2cFuture<R> {
+    shared: Arc<I2cFutureShared<R>>,
+}
+
+impl<R: Send + 'static> I2cFuture<R> {
+    fn new() -> I2cFuture<R> {
+        I2cFuture {
+            shared: Arc::new(I2cFutureShared {
+                state: Mutex::new(I2cFutureState {
+                    result: None,
+                    waker: None,
+                    callback: None,
+                }),
+                done: Condvar::new(),
+            }),
+        }
+    }
+
+    fn ready(result: Result<R, I2cError>) -> I2cFuture<R> {
+        let future = I2cFuture::new();
+        future.shared.complete(result);
+        future
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.shared.lock().result.is_some()
+    }
+
+    // block calling thread until the I/O thread completes the job
+    pub fn wait(self) -> Result<R, I2cError> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(result) = state.result.take() {
+                return result;
+            }
+            state = self
+                .shared
+                .done
+                .wait(state)
+                .unwrap_or_else(|poison| poison.into_inner());
+        }
+    }
+
+    // callback runs on the I/O thread, or immediately when the job is already done
+    pub fn on_complete<F>(self, callback: F)
+    where
+        F: FnOnce(Result<R, I2cError>) + Send + 'static,
+    {
+        let mut state = self.shared.lock();
+        match state.result.take() {
+            Some(result) => {
+                drop(state);
+                callback(result);
+            }
+            None => state.callback = Some(Box::new(callback)),
+        }
+    }
+}
+
+impl<R> Future for I2cFuture<R> {
+    type Output = Result<R, I2cError>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.lock();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// queue metrics, wait is the time a job stays queued before the I/O thread starts it
+#[derive(Clone, Copy, Default, Debug)]
+pub struct I2cAsyncStats {
+    pub pending: usize,
+    pub completed: u64,
+    pub last_wait: Duration,
+    pub max_wait: Duration,
+    pub total_wait: Duration,
+}
+
+impl I2cAsyncStats {
+    pub fn get_avg_wait(&self) -> Duration {
+        match self.completed {
+            0 => Duration::ZERO,
+            count => Duration::from_nanos((self.total_wait.as_nanos() / count as u128) as u64),
+        }
+    }
+}
+
+// non blocking front end of I2cHandle, jobs run in FIFO order on one dedicated I/O thread
+// so ioctls and inter-write delays never block the caller (eg: binder event loop)
+pub struct I2cAsync<B: I2cBackend + 'static = I2cDevBus> {
+    i2c: Arc<I2cHandle<B>>,
+    queue: Mutex<Option<mpsc::Sender<I2cJob<B>>>>,
+    thread: Mutex<Option<thread::JoinHandle<()>>>,
+    stats: Arc<Mutex<I2cAsyncStats>>,
+}
+
+impl<B: I2cBackend + 'static> I2cAsync<B> {
+    pub fn new(i2c: Arc<I2cHandle<B>>) -> Result<I2cAsync<B>, I2cError> {
+        let (sender, receiver) = mpsc::channel::<I2cJob<B>>();
+        let handle = i2c.clone();
+        let thread = thread::Builder::new()
+            .name(format!("i2c-io:{}", i2c.get_name()))
+            .spawn(move || {
+                for job in receiver {
+                    job(&handle);
+                }
+            })
+            .map_err(|error| {
+                I2cError::IO(
+                    error.raw_os_error().unwrap_or(cglue::EAGAIN),
+                    format!("bus:{} could not start I/O thread {}", i2c.get_name(), error),
+                )
+            })?;
+
+        Ok(I2cAsync {
+            i2c,
+            queue: Mutex::new(Some(sender)),
+            thread: Mutex::new(Some(thread)),
+            stats: Arc::new(Mutex::new(I2cAsyncStats::default())),
+        })
+    }
+
+    pub fn get_handle(&self) -> &Arc<I2cHandle<B>> {
+        &self.i2c
+    }
+
+    // pending counts queued jobs plus the one running
+    pub fn get_stats(&self) -> I2cAsyncStats {
+        *self.stats.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    // queue any closure on the I/O thread, a panic inside the job is returned as an error
+    pub fn run<R, F>(&self, func: F) -> I2cFuture<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&I2cHandle<B>) -> Result<R, I2cError> + Send + 'static,
+    {
+        let future = I2cFuture::new();
+        let shared = future.shared.clone();
+        let stats = self.stats.clone();
+        let queued = Instant::now();
+        let job: I2cJob<B> = Box::new(move |i2c| {
+            let wait = queued.elapsed();
+            {
+                let mut stats = stats.lock().unwrap_or_else(|poison| poison.into_inner());
+                stats.last_wait = wait;
+                stats.max_wait = stats.max_wait.max(wait);
+                stats.total_wait += wait;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| func(i2c))).unwrap_or_else(|_| {
+                Err(I2cError::IO(
+                    cglue::EIO,
+                    format!("bus:{} I/O job panicked", i2c.get_name()),
+                ))
+            });
+            {
+                let mut stats = stats.lock().unwrap_or_else(|poison| poison.into_inner());
+                stats.pending -= 1;
+                stats.completed += 1;
+            }
+            shared.complete(result);
+        });
+
+        // counted before send, the I/O thread may complete the job before send returns
+        self.stats.lock().unwrap_or_else(|poison| poison.into_inner()).pending += 1;
+        let queue = self.queue.lock().unwrap_or_else(|poison| poison.into_inner());
+        let sent = match queue.as_ref() {
+            Some(sender) => sender.send(job).is_ok(),
+            None => false,
+        };
+        if !sent {
+            self.stats.lock().unwrap_or_else(|poison| poison.into_inner()).pending -= 1;
+            return I2cFuture::ready(Err(I2cError::IO(
+                cglue::EBADF,
+                format!("bus:{} I/O thread stopped", self.i2c.get_name()),
+            )));
+        }
+        future
+    }
+
+    pub fn read<T>(&self, addr: u32, reg: u8) -> I2cFuture<T>
+    where
+        B: I2cDataCmd<T>,
+        T: Clone + Into<SmbusData> + Send + 'static,
+    {
+        self.run(move |i2c| i2c.read::<T>(addr, reg))
+    }
+
+    pub fn write<T>(&self, addr: u32, reg: u8, data: T) -> I2cFuture<()>
+    where
+        B: I2cDataCmd<T>,
+        T: Clone + Into<SmbusData> + Send + 'static,
+    {
+        self.run(move |i2c| i2c.write(addr, reg, data))
+    }
+
+    pub fn transfer(&self, msgs: Vec<I2cMsg>) -> I2cFuture<Vec<Vec<u8>>> {
+        self.run(move |i2c| i2c.transfer(&msgs))
+    }
+
+    // writes separated by delay, the I/O thread waits between writes, not the caller
+    pub fn write_sequence<T>(&self, addr: u32, reg: u8, values: Vec<T>, delay: Option<Duration>) -> I2cFuture<()>
+    where
+        B: I2cDataCmd<T>,
+        T: Clone + Into<SmbusData> + Send + 'static,
+    {
+        self.run(move |i2c| {
+            let count = values.len();
+            for (idx, value) in values.into_iter().enumerate() {
+                i2c.write(addr, reg, value)?;
+                if let Some(delay) = delay {
+                    if idx + 1 < count {
+                        thread::sleep(delay);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    // stop accepting jobs, already queued jobs complete before the thread exits
+    pub fn shutdown(&self) {
+        self.queue.lock().unwrap_or_else(|poison| poison.into_inner()).take();
+        let thread = self.thread.lock().unwrap_or_else(|poison| poison.into_inner()).take();
+        if let Some(thread) = thread {
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+impl<B: I2cBackend + 'static> Drop for I2cAsync<B> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sim_async() -> I2cAsync<I2cSimBus> {
+        let bus = I2cSimBus::new("sim-async").add_device(I2cSimDevice::new(0x20).set_registers(0x00, &[0x5A]));
+        I2cAsync::new(Arc::new(I2cHandle::from_backend(bus).unwrap())).unwrap()
+    }
+
+    // holds the I/O thread until the returned sender is dropped or used
+    fn block_worker(bus: &I2cAsync<I2cSimBus>) -> (mpsc::Sender<()>, I2cFuture<()>) {
+        let (release, gate) = mpsc::channel::<()>();
+        let future = bus.run(move |_i2c| {
+            let _ = gate.recv();
+            Ok(())
+        });
+        (release, future)
+    }
+
+    #[test]
+    fn jobs_run_in_fifo_order() {
+        let bus = sim_async();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let futures: Vec<I2cFuture<()>> = (0..8)
+            .map(|idx| {
+                let order = order.clone();
+                bus.run(move |_i2c| {
+                    order.lock().unwrap().push(idx);
+                    Ok(())
+                })
+            })
+            .collect();
+        for future in futures {
+            future.wait().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn wait_returns_job_result() {
+        let bus = sim_async();
+        assert_eq!(bus.read::<u8>(0x20, 0x00).wait().unwrap(), 0x5A);
+        bus.write(0x20, 0x01, 0xA5u8).wait().unwrap();
+        assert_eq!(bus.read::<u8>(0x20, 0x01).wait().unwrap(), 0xA5);
+        assert!(matches!(bus.read::<u8>(0x21, 0x00).wait(), Err(I2cError::NO_ACK(_))));
+    }
+
+    #[test]
+    fn on_complete_before_and_after_completion() {
+        let bus = sim_async();
+        let (sender, receiver) = mpsc::channel();
+
+        // job already done, callback runs at once on the calling thread
+        let future = bus.read::<u8>(0x20, 0x00);
+        while !future.is_done() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let done = sender.clone();
+        future.on_complete(move |result| {
+            done.send((result.unwrap(), thread::current().name().map(String::from))).unwrap();
+        });
+        assert_eq!(receiver.try_recv().unwrap(), (0x5A, thread::current().name().map(String::from)));
+
+        // job still queued, callback runs later on the I/O thread
+        let (release, _blocker) = block_worker(&bus);
+        bus.read::<u8>(0x20, 0x00).on_complete(move |result| {
+            sender.send((result.unwrap(), thread::current().name().map(String::from))).unwrap();
+        });
+        assert!(receiver.try_recv().is_err());
+        release.send(()).unwrap();
+        let (value, thread) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(value, 0x5A);
+        assert_eq!(thread.as_deref(), Some("i2c-io:sim-async"));
+    }
+
+    #[test]
+    fn panics_do_not_stop_the_worker() {
+        let bus = sim_async();
+        let result = bus.run(|_i2c| -> Result<(), I2cError> { panic!("job panic") }).wait();
+        assert!(matches!(result, Err(I2cError::IO(cglue::EIO, _))));
+
+        // callback registered while the job is queued, so it panics on the I/O thread
+        let (release, _blocker) = block_worker(&bus);
+        bus.read::<u8>(0x20, 0x00).on_complete(|_result| panic!("callback panic"));
+        release.send(()).unwrap();
+        assert_eq!(bus.read::<u8>(0x20, 0x00).wait().unwrap(), 0x5A);
+    }
+
+    #[test]
+    fn shutdown_drains_queued_jobs() {
+        let bus = sim_async();
+        let (release, blocker) = block_worker(&bus);
+        let queued: Vec<I2cFuture<u8>> = (0..3).map(|_| bus.read::<u8>(0x20, 0x00)).collect();
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            release.send(()).unwrap();
+        });
+        bus.shutdown();
+        releaser.join().unwrap();
+
+        blocker.wait().unwrap();
+        for future in queued {
+            assert!(future.is_done());
+            assert_eq!(future.wait().unwrap(), 0x5A);
+        }
+        assert!(matches!(bus.read::<u8>(0x20, 0x00).wait(), Err(I2cError::IO(cglue::EBADF, _))));
+    }
+
+    #[test]
+    fn stats_count_pending_and_completed() {
+        let bus = sim_async();
+        let (release, blocker) = block_worker(&bus);
+        let queued = bus.read::<u8>(0x20, 0x00);
+        assert_eq!(bus.get_stats().pending, 2);
+
+        release.send(()).unwrap();
+        blocker.wait().unwrap();
+        queued.wait().unwrap();
+        bus.read::<u8>(0x20, 0x00).wait().unwrap();
+
+        let stats = bus.get_stats();
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.completed, 3);
+        assert!(stats.max_wait >= stats.last_wait);
+        assert!(stats.get_avg_wait() <= stats.max_wait);
+        assert_eq!(stats.get_avg_wait(), stats.total_wait / 3);
+    }
+}
@@ -0,0 +1,199 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.kernel.org/i2c/smbus-protocol.html
+ *  https://docs.kernel.org/i2c/dev-interface.html
+ *
+ */
+
+use crate::prelude::*;
+use std::time::Duration;
+
+// max data bytes within one SMBus block transfer
+pub const I2C_SMBUS_BLOCK_MAX: usize = 32;
+
+// i2c_msg flags (linux/i2c.h)
+pub const I2C_M_RD: u16 = 0x0001;
+pub const I2C_M_TEN: u16 = 0x0010;
+pub const I2C_M_IGNORE_NAK: u16 = 0x1000;
+pub const I2C_M_NOSTART: u16 = 0x4000;
+
+// I2C_RDWR kernel limits (linux/i2c-dev.h)
+pub const I2C_RDWR_IOCTL_MAX_MSGS: usize = 42;
+pub const I2C_MSG_MAX_LEN: usize = 8192;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SmbusRw {
+    READ,
+    WRITE,
+}
+
+// mirror kernel I2C_SMBUS_xxx transaction types
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SmbusSize {
+    QUICK,
+    BYTE,
+    BYTE_DATA,
+    WORD_DATA,
+    PROC_CALL,
+    BLOCK_DATA,
+    BLOCK_PROC_CALL,
+    I2C_BLOCK_DATA,
+}
+
+// I2C_BLOCK_DATA reads take a zeroed BLOCK whose len is the expected byte count
+#[derive(Clone, PartialEq, Debug)]
+pub enum SmbusData {
+    NONE,
+    BYTE(u8),
+    WORD(u16),
+    BLOCK(Vec<u8>),
+}
+
+impl SmbusData {
+    // raw bytes in SMBus wire order, words are little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SmbusData::NONE => Vec::new(),
+            SmbusData::BYTE(value) => vec![*value],
+            SmbusData::WORD(value) => value.to_le_bytes().to_vec(),
+            SmbusData::BLOCK(values) => values.clone(),
+        }
+    }
+
+    // reverse of to_bytes: 1 byte is a BYTE, 2 bytes a WORD, anything else a BLOCK
+    pub fn from_bytes(values: Vec<u8>) -> SmbusData {
+        match values.len() {
+            1 => SmbusData::BYTE(values[0]),
+            2 => SmbusData::WORD(u16::from_le_bytes([values[0], values[1]])),
+            _ => SmbusData::BLOCK(values),
+        }
+    }
+}
+
+impl From<u8> for SmbusData {
+    fn from(value: u8) -> Self {
+        SmbusData::BYTE(value)
+    }
+}
+
+impl From<u16> for SmbusData {
+    fn from(value: u16) -> Self {
+        SmbusData::WORD(value)
+    }
+}
+
+impl From<i8> for SmbusData {
+    fn from(value: i8) -> Self {
+        SmbusData::BYTE(value as u8)
+    }
+}
+
+impl From<i16> for SmbusData {
+    fn from(value: i16) -> Self {
+        SmbusData::WORD(value as u16)
+    }
+}
+
+// 32bit values travel as 4 bytes I2C block in SMBus (little-endian) order
+impl From<u32> for SmbusData {
+    fn from(value: u32) -> Self {
+        SmbusData::BLOCK(value.to_le_bytes().to_vec())
+    }
+}
+
+impl From<i32> for SmbusData {
+    fn from(value: i32) -> Self {
+        SmbusData::BLOCK(value.to_le_bytes().to_vec())
+    }
+}
+
+impl From<Vec<u8>> for SmbusData {
+    fn from(values: Vec<u8>) -> Self {
+        SmbusData::BLOCK(values)
+    }
+}
+
+// one segment of a combined I2C_RDWR transaction, for read segments
+// buf length defines how many bytes are expected from the device
+#[derive(Clone, Debug)]
+pub struct I2cMsg {
+    pub addr: u16,
+    pub flags: u16,
+    pub buf: Vec<u8>,
+}
+
+impl I2cMsg {
+    pub fn write(addr: u16, data: &[u8]) -> I2cMsg {
+        I2cMsg {
+            addr,
+            flags: 0,
+            buf: data.to_vec(),
+        }
+    }
+
+    pub fn read(addr: u16, len: usize) -> I2cMsg {
+        I2cMsg {
+            addr,
+            flags: I2C_M_RD,
+            buf: vec![0; len],
+        }
+    }
+
+    // skip (repeated) start and address, data is sent in continuation of previous segment
+    pub fn no_start(mut self) -> Self {
+        self.flags |= I2C_M_NOSTART;
+        self
+    }
+
+    pub fn ignore_nak(mut self) -> Self {
+        self.flags |= I2C_M_IGNORE_NAK;
+        self
+    }
+
+    pub fn ten_bit(mut self) -> Self {
+        self.flags |= I2C_M_TEN;
+        self
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.flags & I2C_M_RD != 0
+    }
+}
+
+// Bus backend used by I2cHandle, it hides how bytes reach the device
+// (kernel char device, in-memory simulator, remote bridge, ...)
+// Send is required to share I2cHandle between threads behind its bus lock
+pub trait I2cBackend: Send {
+    fn open(&self) -> Result<(), I2cError>;
+    fn close(&self);
+    fn get_name(&self) -> &str;
+    fn get_funcs(&self) -> Result<I2cFuncs, I2cError>;
+    fn set_timeout(&self, timeout: Duration) -> Result<(), I2cError>;
+    fn set_retries(&self, retries: u32) -> Result<(), I2cError>;
+    fn set_tenbit(&self, enable: bool) -> Result<(), I2cError>;
+    // force skips the kernel check on addresses already bound to a driver
+    fn set_address(&self, addr: u32, force: bool) -> Result<(), I2cError>;
+    // name of the kernel driver owning addr if any
+    fn get_driver(&self, _addr: u32) -> Option<String> {
+        None
+    }
+    fn set_pec(&self, enable: bool) -> Result<(), I2cError>;
+    fn smbus_xfer(
+        &self,
+        rw: SmbusRw,
+        command: u8,
+        size: SmbusSize,
+        data: SmbusData,
+    ) -> Result<SmbusData, I2cError>;
+    fn transfer(&self, msgs: &mut [I2cMsg]) -> Result<(), I2cError>;
+}
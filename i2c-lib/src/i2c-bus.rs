@@ -16,163 +16,1144 @@
  */
 
 use crate::prelude::*;
-use afbv4::prelude::*;
-use std::cell::Cell;
-use std::ffi::CStr;
-use std::ffi::CString;
-use std::str;
+use std::collections::HashMap;
+use std::ops::{BitAnd, BitOr, Not, RangeInclusive};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
 
-pub struct I2cHandle {
-    devname: CString,
-    raw_fd: Cell<i32>,
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cAddrMode {
+    #[default]
+    BIT7,
+    BIT10,
 }
 
-impl I2cHandle {
-    #[track_caller]
-    pub fn new(i2cbus: &'static str) -> Result<I2cHandle, AfbError> {
-        let devname = match CString::new(i2cbus) {
-            Err(_) => {
-                return afb_error!(
-                    "serial-invalid-devname",
-                    "fail to convert name to UTF8",
-                )
+// register pointer size, 16bit maps (24C32+ eeprom, large devices) use combined transfers
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cRegWidth {
+    #[default]
+    BIT8,
+    BIT16,
+}
+
+// per slave address options, applied around each transaction
+#[derive(Clone, Copy, Default, Debug)]
+pub struct I2cAddrCfg {
+    pub pec: bool,
+    pub mode: I2cAddrMode,
+    pub allow_reserved: bool,
+    pub force: bool,
+    pub reg_width: I2cRegWidth,
+    pub reg_endian: I2cEndian,
+}
+
+// probing strategy, AUTO follows i2cdetect: read-byte on 0x30-0x37 & 0x50-0x5F
+// (quick-write may lock some eeprom/write-protect chips), quick-write elsewhere
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cScanMode {
+    #[default]
+    AUTO,
+    QUICK,
+    READ,
+}
+
+// addresses answering the probe and addresses skipped because claimed by a kernel driver
+#[derive(Clone, Default, Debug)]
+pub struct I2cScanResult {
+    pub found: Vec<u32>,
+    pub busy: Vec<u32>,
+}
+
+// i2cdump access modes, WORD reads every even register like i2cdump does
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cDumpMode {
+    #[default]
+    BYTE,
+    WORD,
+    BLOCK,
+}
+
+// 7bit addresses reserved by the I2C specification (general call, cbus, hs-mode, 10bit prefix)
+pub fn i2c_addr_reserved(addr: u32) -> bool {
+    addr <= 0x07 || (0x78..=0x7F).contains(&addr)
+}
+
+// handle is Send+Sync, backend lock is held from slave selection to transaction end
+// so concurrent callers (threads, timers, apis) never talk to the wrong device
+pub struct I2cHandle<B: I2cBackend = I2cDevBus> {
+    name: String,
+    backend: Mutex<B>,
+    funcs: I2cFuncs,
+    addrs: Mutex<HashMap<u32, I2cAddrCfg>>,
+    timeout: Mutex<Option<Duration>>,
+    retries: Mutex<Option<u32>>,
+    tracer: RwLock<Option<Box<dyn I2cTraceSink>>>,
+    recovery: Mutex<I2cRecoveryCtx>,
+    recovering: AtomicBool,
+    on_state: RwLock<Option<I2cStateHook<B>>>,
+}
+
+impl I2cHandle<I2cDevBus> {
+    pub fn new(i2cbus: &str) -> Result<I2cHandle<I2cDevBus>, I2cError> {
+        I2cHandle::from_backend(I2cDevBus::new(i2cbus)?)
+    }
+
+    pub fn open_adapter(name: &str, sysfs: &I2cSysfs) -> Result<I2cHandle<I2cDevBus>, I2cError> {
+        I2cHandle::from_backend(I2cDevBus::from_adapter(name, sysfs)?)
+    }
+
+    pub fn get_fd(&self) -> i32 {
+        self.lock_backend().get_fd()
+    }
+}
+
+impl<B: I2cBackend> I2cHandle<B> {
+    pub fn from_backend(backend: B) -> Result<I2cHandle<B>, I2cError> {
+        // open the line before returning the handle
+        backend.open()?;
+
+        // adapter functionality matrix is queried only once
+        let funcs = match backend.get_funcs() {
+            Err(error) => {
+                return Err(error.context(format!(
+                    "bus:{} could not get adapter functionality matrix",
+                    backend.get_name()
+                )))
             }
             Ok(value) => value,
         };
 
-        let handle = I2cHandle {
-            devname: devname,
-            raw_fd: Cell::new(0),
-        };
+        Ok(I2cHandle {
+            name: backend.get_name().to_string(),
+            backend: Mutex::new(backend),
+            funcs,
+            addrs: Mutex::new(HashMap::new()),
+            timeout: Mutex::new(None),
+            retries: Mutex::new(None),
+            tracer: RwLock::new(None),
+            recovery: Mutex::new(I2cRecoveryCtx::new()),
+            recovering: AtomicBool::new(false),
+            on_state: RwLock::new(None),
+        })
+    }
 
-        // open the line before returning the handle
-        let _ = &handle.open()?;
-        Ok(handle)
+    // a panic while holding the lock does not corrupt the fd, ignore poisoning
+    fn lock_backend(&self) -> MutexGuard<'_, B> {
+        self.backend.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    fn lock_recovery(&self) -> MutexGuard<'_, I2cRecoveryCtx> {
+        self.recovery.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    fn lock_addrs(&self) -> MutexGuard<'_, HashMap<u32, I2cAddrCfg>> {
+        self.addrs.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    pub fn open(&self) -> Result<(), I2cError> {
+        self.open_locked(&self.lock_backend())
     }
 
-    #[track_caller]
-    pub fn open(&self) -> Result<(), AfbError> {
-        // open tty i2cbus
-        let raw_fd = unsafe { cglue::open(self.devname.as_ptr(), cglue::BUS_I2C_O_RDWR, 0) };
-        if raw_fd < 0 {
-            return afb_error!("serial-open-fail","dev:{:?} error:{}", self.devname, get_perror())
+    fn open_locked(&self, backend: &B) -> Result<(), I2cError> {
+        backend.open()?;
+
+        // adapter settings are attached to the fd, re-apply them on every (re)open
+        if let Some(timeout) = *self.timeout.lock().unwrap_or_else(|poison| poison.into_inner()) {
+            self.apply_timeout(backend, timeout)?;
         }
+        if let Some(retries) = *self.retries.lock().unwrap_or_else(|poison| poison.into_inner()) {
+            self.apply_retries(backend, retries)?;
+        }
+        Ok(())
+    }
 
-        // apply i2c init commands if any (TBD Fulup)
+    fn apply_timeout(&self, backend: &B, timeout: Duration) -> Result<(), I2cError> {
+        if let Err(error) = backend.set_timeout(timeout) {
+            return Err(error.context(format!(
+                "bus:{} could not set timeout:{:?}",
+                self.get_name(), timeout
+            )));
+        }
+        Ok(())
+    }
 
-        // update fd cell within immutable handle
-        self.raw_fd.set(raw_fd);
-        afb_log_msg!(Debug, None, "Open port={:?}", self.devname);
+    fn apply_retries(&self, backend: &B, retries: u32) -> Result<(), I2cError> {
+        if let Err(error) = backend.set_retries(retries) {
+            return Err(error.context(format!(
+                "bus:{} could not set retries:{}",
+                self.get_name(), retries
+            )));
+        }
+        Ok(())
+    }
 
+    // adapter timeout, kernel granularity is 10ms
+    pub fn set_timeout(&self, timeout: Duration) -> Result<(), I2cError> {
+        self.apply_timeout(&self.lock_backend(), timeout)?;
+        *self.timeout.lock().unwrap_or_else(|poison| poison.into_inner()) = Some(timeout);
         Ok(())
     }
 
+    // number of times the adapter retries a transaction on arbitration lost
+    pub fn set_retries(&self, retries: u32) -> Result<(), I2cError> {
+        self.apply_retries(&self.lock_backend(), retries)?;
+        *self.retries.lock().unwrap_or_else(|poison| poison.into_inner()) = Some(retries);
+        Ok(())
+    }
+
+    // transaction observer, handle is silent when None (default)
+    pub fn set_tracer(&self, tracer: Option<Box<dyn I2cTraceSink>>) {
+        *self.tracer.write().unwrap_or_else(|poison| poison.into_inner()) = tracer;
+    }
+
+    // value is only computed when a tracer is installed
+    #[allow(clippy::too_many_arguments)]
+    fn trace<V>(
+        &self,
+        addr: u32,
+        reg: Option<u16>,
+        rw: SmbusRw,
+        kind: I2cTraceKind,
+        start: Instant,
+        value: V,
+        error: Option<&I2cError>,
+    ) where
+        V: FnOnce() -> SmbusData,
+    {
+        let tracer = self.tracer.read().unwrap_or_else(|poison| poison.into_inner());
+        if let Some(tracer) = tracer.as_ref() {
+            tracer.trace(&I2cTraceEvent {
+                bus: self.get_name(),
+                addr,
+                reg,
+                rw,
+                kind,
+                value: value(),
+                duration: start.elapsed(),
+                error,
+            });
+        }
+    }
+
+    // reopen bus on fatal errors, None (default) keeps failing until explicit reopen
+    pub fn set_recovery(&self, policy: Option<I2cRecovery>) {
+        let mut recovery = self.lock_recovery();
+        recovery.policy = policy;
+        if policy.is_none() {
+            recovery.reset();
+        }
+    }
+
+    // state change observer, RECOVERED is called once bus is reopened
+    pub fn set_state_hook(&self, hook: Option<I2cStateHook<B>>) {
+        *self.on_state.write().unwrap_or_else(|poison| poison.into_inner()) = hook;
+    }
+
+    pub fn get_state(&self) -> I2cBusState {
+        self.lock_recovery().state
+    }
+
+    fn notify_state(&self, state: I2cBusState) {
+        let hook = self.on_state.read().unwrap_or_else(|poison| poison.into_inner());
+        if let Some(hook) = hook.as_ref() {
+            hook(self, state);
+        }
+    }
+
+    // degraded bus fails fast until backoff delay elapsed, then tries to reopen
+    fn check_recovery(&self) -> Result<(), I2cError> {
+        let (state, next_attempt) = {
+            let recovery = self.lock_recovery();
+            (recovery.state, recovery.next_attempt)
+        };
+        if state != I2cBusState::DEGRADED || self.recovering.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let now = Instant::now();
+        if now < next_attempt {
+            return Err(I2cError::IO(
+                cglue::ENODEV,
+                format!(
+                    "bus:{} degraded, next reopen in {:?}",
+                    self.get_name(),
+                    next_attempt - now
+                ),
+            ));
+        }
+        self.recover()
+    }
+
+    // called with bus lock released, returns true when the bus was reopened after ENODEV/EBADF:
+    // the request never reached the device and the caller replays it once. A reopen after
+    // consecutive EIO returns false, the failed transfer may have been partly done.
+    fn check_fatal<R>(&self, result: &Result<R, I2cError>) -> bool {
+        let fatal = match result {
+            Err(error) if error.is_fatal() => true,
+            Err(error) if error.is_eio() => false,
+            _ => {
+                self.lock_recovery().eio_count = 0;
+                return false;
+            }
+        };
+        if self.recovering.load(Ordering::Acquire) {
+            return false;
+        }
+        {
+            let mut recovery = self.lock_recovery();
+            let policy = match recovery.policy {
+                Some(policy) if recovery.state != I2cBusState::DEGRADED => policy,
+                _ => return false,
+            };
+            if !fatal {
+                recovery.eio_count += 1;
+                if policy.eio_threshold == 0 || recovery.eio_count < policy.eio_threshold {
+                    return false;
+                }
+            }
+            recovery.eio_count = 0;
+        }
+        self.recover().is_ok() && fatal
+    }
+
+    // reopen bus, on failure double delay up to max_delay before next attempt
+    fn recover(&self) -> Result<(), I2cError> {
+        if self.recovering.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let previous = self.get_state();
+        let result = self.reopen();
+        match &result {
+            Ok(()) => {
+                self.lock_recovery().state = I2cBusState::RECOVERED;
+                self.notify_state(I2cBusState::RECOVERED);
+                self.lock_recovery().reset();
+            }
+            Err(_) => {
+                self.lock_recovery().backoff();
+                if previous != I2cBusState::DEGRADED {
+                    self.notify_state(I2cBusState::DEGRADED);
+                }
+            }
+        }
+        self.recovering.store(false, Ordering::Release);
+        result.map_err(|error| error.context(format!("bus:{} reopen failed", self.get_name())))
+    }
+
     pub fn close(&self) {
-        unsafe { cglue::close(self.raw_fd.get()) };
+        self.lock_backend().close()
     }
 
-    #[track_caller]
-    pub fn reopen(&self) -> Result<(), AfbError> {
-        self.close();
-        self.open()
+    // one bus lock across close and open, no transaction may run on the closed fd
+    pub fn reopen(&self) -> Result<(), I2cError> {
+        let backend = self.lock_backend();
+        backend.close();
+        self.open_locked(&backend)
     }
 
-    pub fn get_fd(&self) -> i32 {
-        self.raw_fd.get()
+    // run a closure with exclusive access to the backend (eg: simulator inspection)
+    pub fn with_backend<R, F: FnOnce(&B) -> R>(&self, func: F) -> R {
+        func(&self.lock_backend())
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn get_funcs(&self) -> I2cFuncs {
+        self.funcs
+    }
+
+    pub fn check_func(&self, func: I2cFunc) -> Result<(), I2cError> {
+        if !self.funcs.has(func) {
+            return Err(I2cError::UNSUPPORTED(format!(
+                "bus:{} adapter does not have {} capability",
+                self.get_name(), func
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn get_addr_cfg(&self, addr: u32) -> I2cAddrCfg {
+        match self.lock_addrs().get(&addr) {
+            Some(cfg) => *cfg,
+            None => I2cAddrCfg::default(),
+        }
+    }
+
+    // SMBus packet error checking for every transaction with this slave
+    pub fn set_pec(&self, addr: u32, enable: bool) -> Result<(), I2cError> {
+        if enable && !self.funcs.has(I2cFunc::SMBUS_PEC) && !self.funcs.has(I2cFunc::I2C) {
+            return Err(I2cError::UNSUPPORTED(format!(
+                "bus:{} adapter does not have {} capability",
+                self.get_name(), I2cFunc::SMBUS_PEC
+            )));
+        }
+        self.lock_addrs().entry(addr).or_default().pec = enable;
+        Ok(())
+    }
+
+    // 10bit addressing uses the kernel I2C_TENBIT mode for this slave
+    pub fn set_addr_mode(&self, addr: u32, mode: I2cAddrMode) -> Result<(), I2cError> {
+        if mode == I2cAddrMode::BIT10 {
+            self.check_func(I2cFunc::TEN_BIT_ADDR)?;
+        }
+        self.lock_addrs().entry(addr).or_default().mode = mode;
+        Ok(())
+    }
+
+    // explicitly allow talking to a reserved 7bit address
+    pub fn set_allow_reserved(&self, addr: u32, allow: bool) {
+        self.lock_addrs().entry(addr).or_default().allow_reserved = allow;
+    }
+
+    // use I2C_SLAVE_FORCE even if a kernel driver is bound to this slave
+    pub fn set_force(&self, addr: u32, force: bool) {
+        self.lock_addrs().entry(addr).or_default().force = force;
+    }
+
+    // register pointer width and byte order used by read_at/write_at for this slave
+    pub fn set_reg_width(&self, addr: u32, width: I2cRegWidth, endian: I2cEndian) -> Result<(), I2cError> {
+        if width == I2cRegWidth::BIT16 {
+            self.check_func(I2cFunc::I2C)?;
+        }
+        let mut addrs = self.lock_addrs();
+        let cfg = addrs.entry(addr).or_default();
+        cfg.reg_width = width;
+        cfg.reg_endian = endian;
+        Ok(())
+    }
+
+    // register pointer bytes as sent on the wire
+    fn reg_pointer(&self, addr: u32, reg: u16) -> Result<Vec<u8>, I2cError> {
+        let cfg = self.get_addr_cfg(addr);
+        match (cfg.reg_width, cfg.reg_endian) {
+            (I2cRegWidth::BIT8, _) if reg > 0xFF => Err(I2cError::INVALID_ARG(format!(
+                "addr:{:#02x} register:{:#04x} out of 8bit range (check reg-width)",
+                addr, reg
+            ))),
+            (I2cRegWidth::BIT8, _) => Ok(vec![reg as u8]),
+            (I2cRegWidth::BIT16, I2cEndian::BIG) => Ok(reg.to_be_bytes().to_vec()),
+            (I2cRegWidth::BIT16, I2cEndian::LITTLE) => Ok(reg.to_le_bytes().to_vec()),
+        }
+    }
+
+    pub fn check_reg(&self, addr: u32, reg: u16) -> Result<(), I2cError> {
+        self.reg_pointer(addr, reg)?;
+        Ok(())
+    }
+
+    pub fn check_addr(&self, addr: u32) -> Result<(), I2cError> {
+        let cfg = self.get_addr_cfg(addr);
+        match cfg.mode {
+            I2cAddrMode::BIT10 => {
+                if addr > 0x3FF {
+                    return Err(I2cError::INVALID_ARG(format!(
+                        "addr:{:#03x} out of 10bit range 0x000-0x3FF",
+                        addr
+                    )));
+                }
+            }
+            I2cAddrMode::BIT7 => {
+                if addr > 0x7F {
+                    return Err(I2cError::INVALID_ARG(format!(
+                        "addr:{:#02x} out of 7bit range 0x00-0x7F (check addr-mode)",
+                        addr
+                    )));
+                }
+                if i2c_addr_reserved(addr) && !cfg.allow_reserved {
+                    return Err(I2cError::INVALID_ARG(format!(
+                        "addr:{:#02x} is reserved (0x00-0x07 & 0x78-0x7F)",
+                        addr
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // i2cdetect equivalent on a 7bit range, addresses not supported by the adapter
+    // in AUTO mode are silently skipped like i2cdetect does
+    pub fn scan(&self, first: u32, last: u32, mode: I2cScanMode) -> Result<I2cScanResult, I2cError> {
+        self.check_recovery()?;
+        let mut result = self.scan_locked(first, last, mode);
+        if self.check_fatal(&result) {
+            result = self.scan_locked(first, last, mode);
+            self.check_fatal(&result);
+        }
+        result
     }
 
-    pub fn get_name<'a>(&'a self) -> &'a CStr {
-        self.devname.as_c_str()
+    fn scan_locked(&self, first: u32, last: u32, mode: I2cScanMode) -> Result<I2cScanResult, I2cError> {
+        if first > last || last > 0x7F {
+            return Err(I2cError::INVALID_ARG(format!(
+                "invalid range:{:#02x}-{:#02x} should be within 0x00-0x7F",
+                first, last
+            )));
+        }
+        match mode {
+            I2cScanMode::QUICK => self.check_func(I2cFunc::SMBUS_QUICK)?,
+            I2cScanMode::READ => self.check_func(I2cFunc::SMBUS_READ_BYTE)?,
+            I2cScanMode::AUTO => {
+                if !self.funcs.has(I2cFunc::SMBUS_QUICK) && !self.funcs.has(I2cFunc::SMBUS_READ_BYTE) {
+                    return Err(I2cError::UNSUPPORTED(format!(
+                        "bus:{} adapter has neither {} nor {} capability",
+                        self.get_name(),
+                        I2cFunc::SMBUS_QUICK,
+                        I2cFunc::SMBUS_READ_BYTE
+                    )));
+                }
+            }
+        }
+
+        // whole scan runs under the bus lock
+        let backend = self.lock_backend();
+        if let Err(error) = backend.set_tenbit(false) {
+            return Err(error.context(format!("bus:{} could not set 7bit mode", self.get_name())));
+        }
+
+        let mut result = I2cScanResult::default();
+        for addr in first..=last {
+            let size = match mode {
+                I2cScanMode::QUICK => SmbusSize::QUICK,
+                I2cScanMode::READ => SmbusSize::BYTE,
+                I2cScanMode::AUTO => {
+                    if (0x30..=0x37).contains(&addr) || (0x50..=0x5F).contains(&addr) {
+                        SmbusSize::BYTE
+                    } else {
+                        SmbusSize::QUICK
+                    }
+                }
+            };
+            if !self.funcs.has(I2cFunc::from_smbus(SmbusRw::READ, size)) {
+                continue;
+            }
+
+            match backend.set_address(addr, false) {
+                Err(I2cError::ADDR_BUSY(_)) => {
+                    result.busy.push(addr);
+                    continue;
+                }
+                Err(error) => {
+                    return Err(error.context(format!(
+                        "bus:{} could not select addr:{:#02x}",
+                        self.get_name(), addr
+                    )));
+                }
+                Ok(()) => {}
+            }
+
+            // quick-write sends rw bit 0 without any data byte
+            let probe = match size {
+                SmbusSize::QUICK => backend.smbus_xfer(SmbusRw::WRITE, 0, size, SmbusData::NONE),
+                _ => backend.smbus_xfer(SmbusRw::READ, 0, size, SmbusData::NONE),
+            };
+            // a dead adapter is not an empty address, let check_fatal reopen the bus
+            match probe {
+                Ok(_) => result.found.push(addr),
+                Err(error) if error.is_fatal() => {
+                    return Err(error.context(format!("bus:{} probe addr:{:#02x}", self.get_name(), addr)));
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(result)
     }
 
-    #[track_caller]
-    pub fn read<T>(&self, addr: u32, reg: u8) -> Result<T, AfbError>
+    // i2cdump equivalent, registers failing to read are returned as None (i2cdump 'XX')
+    pub fn dump(
+        &self,
+        addr: u32,
+        range: RangeInclusive<u8>,
+        mode: I2cDumpMode,
+    ) -> Result<Vec<(u8, Option<SmbusData>)>, I2cError> {
+        self.check_addr(addr)?;
+        if self.get_addr_cfg(addr).reg_width == I2cRegWidth::BIT16 {
+            return Err(I2cError::UNSUPPORTED(format!(
+                "addr:{:#02x} 16bit register map cannot be dumped with SMBus, use read_at",
+                addr
+            )));
+        }
+        let (first, last) = (*range.start() as usize, *range.end() as usize);
+        let mut registers = Vec::new();
+
+        match mode {
+            I2cDumpMode::BYTE => {
+                self.check_func(I2cFunc::SMBUS_READ_BYTE_DATA)?;
+                for reg in first..=last {
+                    let data = self.read::<u8>(addr, reg as u8).ok().map(SmbusData::BYTE);
+                    registers.push((reg as u8, data));
+                }
+            }
+            I2cDumpMode::WORD => {
+                self.check_func(I2cFunc::SMBUS_READ_WORD_DATA)?;
+                for reg in (first..=last).step_by(2) {
+                    let data = self.read::<u16>(addr, reg as u8).ok().map(SmbusData::WORD);
+                    registers.push((reg as u8, data));
+                }
+            }
+            // I2C block reads by chunks of 32 registers, split back to one byte per register
+            I2cDumpMode::BLOCK => {
+                self.check_func(I2cFunc::SMBUS_READ_I2C_BLOCK)?;
+                for start in (first..=last).step_by(I2C_SMBUS_BLOCK_MAX) {
+                    let len = (last - start + 1).min(I2C_SMBUS_BLOCK_MAX);
+                    let block = self.read_i2c_block(addr, start as u8, len).ok();
+                    for idx in 0..len {
+                        let data = match &block {
+                            Some(values) => values.get(idx).map(|value| SmbusData::BYTE(*value)),
+                            None => None,
+                        };
+                        registers.push(((start + idx) as u8, data));
+                    }
+                }
+            }
+        }
+        Ok(registers)
+    }
+
+    fn smbus_access<R, F>(&self, rw: SmbusRw, addr: u32, reg: u8, xfer: F) -> Result<R, I2cError>
     where
-        I2cHandle: I2cDataCmd<T>,
+        F: Fn(&B) -> Result<R, I2cError>,
     {
-        let fd = self.raw_fd.get();
+        self.check_recovery()?;
+        let mut result = self.smbus_locked(rw, addr, reg, &xfer);
+        if self.check_fatal(&result) {
+            result = self.smbus_locked(rw, addr, reg, &xfer);
+            self.check_fatal(&result);
+        }
+        result
+    }
+
+    // select slave and apply its options, run transaction then restore shared fd state
+    fn smbus_locked<R, F>(&self, rw: SmbusRw, addr: u32, reg: u8, xfer: F) -> Result<R, I2cError>
+    where
+        F: FnOnce(&B) -> Result<R, I2cError>,
+    {
+        let cfg = self.get_addr_cfg(addr);
+        self.check_addr(addr)?;
+        let ten_bit = cfg.mode == I2cAddrMode::BIT10;
+        let backend = self.lock_backend();
+
+        // slave address should be set after 10bit mode
+        if let Err(error) = backend.set_tenbit(ten_bit) {
+            return Err(error.context(format!(
+                "addr:{:#02x} could not set 10bit mode:{}",
+                addr, ten_bit
+            )));
+        }
+
+        if let Err(error) = backend.set_address(addr, cfg.force) {
+            if let I2cError::ADDR_BUSY(_) = error {
+                let driver = match backend.get_driver(addr) {
+                    Some(value) => value,
+                    None => "unknown".to_string(),
+                };
+                return Err(I2cError::ADDR_BUSY(format!(
+                    "addr:{:#02x} claimed by kernel driver:{} (use force)",
+                    addr, driver
+                )));
+            }
+            return Err(error.context(format!("invalid addr:{:#02x}", addr)));
+        }
 
-        if (unsafe { cglue::ioctl(fd, cglue::BUS_I2C_SLAVE, addr) } < 0) {
-            return afb_error!(
-                "i2c-read-addr",
-                "invalid addr={}", addr
-            );
+        if cfg.pec {
+            if let Err(error) = backend.set_pec(true) {
+                return Err(error.context(format!("addr:{:#02x} could not set PEC", addr)));
+            }
         }
 
-        match I2cHandle::mk_read(fd, reg) {
-            Err(error) => afb_error!(
-                "i2c-read-data",
-                "addr:{} register:{} error:{}", addr, reg, error
-            ),
-            Ok(value) => Ok(value),
+        let result = xfer(&backend);
+
+        if cfg.pec {
+            let _ = backend.set_pec(false);
         }
+
+        let action = match rw {
+            SmbusRw::READ => "read",
+            SmbusRw::WRITE => "write",
+        };
+        result.map_err(|error| error.context(format!(
+            "addr:{:#02x} register:{:#02x} {}",
+            addr, reg, action
+        )))
+    }
+
+    pub fn read<T>(&self, addr: u32, reg: u8) -> Result<T, I2cError>
+    where
+        B: I2cDataCmd<T>,
+        T: Clone + Into<SmbusData>,
+    {
+        let size = <B as I2cDataCmd<T>>::SMBUS_SIZE;
+        self.check_func(I2cFunc::from_smbus(SmbusRw::READ, size))?;
+        let start = Instant::now();
+        let result = self.smbus_access(SmbusRw::READ, addr, reg, |bus| {
+            <B as I2cDataCmd<T>>::mk_read(bus, reg)
+        });
+        let value = || match &result {
+            Ok(data) => data.clone().into(),
+            Err(_) => SmbusData::NONE,
+        };
+        let kind = I2cTraceKind::SMBUS(size);
+        self.trace(addr, Some(reg as u16), SmbusRw::READ, kind, start, value, result.as_ref().err());
+        result
+    }
+
+    pub fn write<T>(&self, addr: u32, reg: u8, data: T) -> Result<(), I2cError>
+    where
+        B: I2cDataCmd<T>,
+        T: Clone + Into<SmbusData>,
+    {
+        let size = <B as I2cDataCmd<T>>::SMBUS_SIZE;
+        self.check_func(I2cFunc::from_smbus(SmbusRw::WRITE, size))?;
+        let value = data.clone();
+        let start = Instant::now();
+        let result = self.smbus_access(SmbusRw::WRITE, addr, reg, |bus| {
+            <B as I2cDataCmd<T>>::mk_write(bus, reg, data.clone())
+        });
+        let kind = I2cTraceKind::SMBUS(size);
+        self.trace(addr, Some(reg as u16), SmbusRw::WRITE, kind, start, || value.into(), result.as_ref().err());
+        result
+    }
+
+    // read-modify-write under one bus lock, register is only written when value changes
+    fn modify<T, F>(&self, addr: u32, reg: u8, func: F) -> Result<T, I2cError>
+    where
+        B: I2cDataCmd<T>,
+        T: Copy + PartialEq + Into<SmbusData>,
+        F: Fn(T) -> T,
+    {
+        let size = <B as I2cDataCmd<T>>::SMBUS_SIZE;
+        self.check_func(I2cFunc::from_smbus(SmbusRw::READ, size))?;
+        self.check_func(I2cFunc::from_smbus(SmbusRw::WRITE, size))?;
+        let start = Instant::now();
+        let result = self.smbus_access(SmbusRw::WRITE, addr, reg, |bus| {
+            let current = <B as I2cDataCmd<T>>::mk_read(bus, reg)?;
+            let value = func(current);
+            if value != current {
+                <B as I2cDataCmd<T>>::mk_write(bus, reg, value)?;
+            }
+            Ok(value)
+        });
+        let value = || match &result {
+            Ok(data) => (*data).into(),
+            Err(_) => SmbusData::NONE,
+        };
+        let kind = I2cTraceKind::SMBUS(size);
+        self.trace(addr, Some(reg as u16), SmbusRw::WRITE, kind, start, value, result.as_ref().err());
+        result
     }
 
-    #[track_caller]
-    pub fn write<T>(&self, addr: u32, reg: u8, data: T) -> Result<(), AfbError>
+    // change only mask bits to value, returns register new content
+    pub fn update_bits<T>(&self, addr: u32, reg: u8, mask: T, value: T) -> Result<T, I2cError>
     where
-        I2cHandle: I2cDataCmd<T>,
+        B: I2cDataCmd<T>,
+        T: Copy + PartialEq + Into<SmbusData> + BitAnd<Output = T> + BitOr<Output = T> + Not<Output = T>,
     {
-        let fd = self.raw_fd.get();
+        self.modify(addr, reg, |current: T| (current & !mask) | (value & mask))
+    }
+
+    fn bit_mask(addr: u32, reg: u8, bit: u8) -> Result<u8, I2cError> {
+        if bit > 7 {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{:#02x} register:{:#02x} bit:{} should be 0-7",
+                addr, reg, bit
+            )));
+        }
+        Ok(1 << bit)
+    }
+
+    pub fn set_bit(&self, addr: u32, reg: u8, bit: u8) -> Result<u8, I2cError> {
+        let mask = Self::bit_mask(addr, reg, bit)?;
+        self.update_bits(addr, reg, mask, mask)
+    }
+
+    pub fn clear_bit(&self, addr: u32, reg: u8, bit: u8) -> Result<u8, I2cError> {
+        let mask = Self::bit_mask(addr, reg, bit)?;
+        self.update_bits(addr, reg, mask, 0)
+    }
+
+    pub fn toggle_bit(&self, addr: u32, reg: u8, bit: u8) -> Result<u8, I2cError> {
+        let mask = Self::bit_mask(addr, reg, bit)?;
+        self.modify(addr, reg, |current: u8| current ^ mask)
+    }
+
+    // SMBus block read, device returns its own byte count (max 32)
+    pub fn read_block(&self, addr: u32, reg: u8) -> Result<Vec<u8>, I2cError> {
+        self.read::<Vec<u8>>(addr, reg)
+    }
+
+    pub fn write_block(&self, addr: u32, reg: u8, data: &[u8]) -> Result<(), I2cError> {
+        if data.len() > I2C_SMBUS_BLOCK_MAX {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{} register:{} len:{} > max:{}",
+                addr, reg, data.len(), I2C_SMBUS_BLOCK_MAX
+            )));
+        }
+        self.write(addr, reg, data.to_vec())
+    }
+
+    // I2C block read, len bytes from consecutive registers starting at reg
+    pub fn read_i2c_block(&self, addr: u32, reg: u8, len: usize) -> Result<Vec<u8>, I2cError> {
+        if len == 0 || len > I2C_SMBUS_BLOCK_MAX {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{} register:{} len:{} should be 1-{}",
+                addr, reg, len, I2C_SMBUS_BLOCK_MAX
+            )));
+        }
+        self.check_func(I2cFunc::SMBUS_READ_I2C_BLOCK)?;
+
+        let start = Instant::now();
+        let result = self.smbus_access(SmbusRw::READ, addr, reg, |bus| {
+            let request = SmbusData::BLOCK(vec![0; len]);
+            match bus.smbus_xfer(SmbusRw::READ, reg, SmbusSize::I2C_BLOCK_DATA, request)? {
+                SmbusData::BLOCK(values) => Ok(values),
+                response => Err(I2cError::IO(
+                    cglue::EPROTO,
+                    format!("unexpected smbus response:{:?}", response),
+                )),
+            }
+        });
+        let value = || match &result {
+            Ok(values) => SmbusData::BLOCK(values.clone()),
+            Err(_) => SmbusData::NONE,
+        };
+        let error = result.as_ref().err();
+        let kind = I2cTraceKind::SMBUS(SmbusSize::I2C_BLOCK_DATA);
+        self.trace(addr, Some(reg as u16), SmbusRw::READ, kind, start, value, error);
+        result
+    }
+
+    pub fn write_i2c_block(&self, addr: u32, reg: u8, data: &[u8]) -> Result<(), I2cError> {
+        if data.is_empty() || data.len() > I2C_SMBUS_BLOCK_MAX {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{} register:{} len:{} should be 1-{}",
+                addr, reg, data.len(), I2C_SMBUS_BLOCK_MAX
+            )));
+        }
+        self.check_func(I2cFunc::SMBUS_WRITE_I2C_BLOCK)?;
+
+        let start = Instant::now();
+        let result = self.smbus_access(SmbusRw::WRITE, addr, reg, |bus| {
+            let request = SmbusData::BLOCK(data.to_vec());
+            bus.smbus_xfer(SmbusRw::WRITE, reg, SmbusSize::I2C_BLOCK_DATA, request)?;
+            Ok(())
+        });
+        let value = || SmbusData::BLOCK(data.to_vec());
+        let error = result.as_ref().err();
+        let kind = I2cTraceKind::SMBUS(SmbusSize::I2C_BLOCK_DATA);
+        self.trace(addr, Some(reg as u16), SmbusRw::WRITE, kind, start, value, error);
+        result
+    }
+
+    // traced SMBus primitive, command is the register or the data byte (send-byte)
+    fn smbus_op(
+        &self,
+        rw: SmbusRw,
+        addr: u32,
+        command: u8,
+        size: SmbusSize,
+        data: SmbusData,
+    ) -> Result<SmbusData, I2cError> {
+        self.check_func(I2cFunc::from_smbus(rw, size))?;
+        let request = data.clone();
+        let start = Instant::now();
+        let result = self.smbus_access(rw, addr, command, |bus| bus.smbus_xfer(rw, command, size, data.clone()));
+        let value = || match &result {
+            Ok(SmbusData::NONE) | Err(_) => request,
+            Ok(response) => response.clone(),
+        };
+        let kind = I2cTraceKind::SMBUS(size);
+        self.trace(addr, Some(command as u16), rw, kind, start, value, result.as_ref().err());
+        result
+    }
+
+    // quick command, the rw bit is the only data sent to the slave
+    pub fn quick(&self, addr: u32, rw: SmbusRw) -> Result<(), I2cError> {
+        self.smbus_op(rw, addr, 0, SmbusSize::QUICK, SmbusData::NONE)?;
+        Ok(())
+    }
 
-        if (unsafe { cglue::ioctl(fd, cglue::BUS_I2C_SLAVE, addr) } < 0) {
-            return afb_error!(
-                "i2c-write-addr",
-                "invalid addr={}", addr
-            );
+    // receive byte without register (eg: PCF8574 port read)
+    pub fn receive_byte(&self, addr: u32) -> Result<u8, I2cError> {
+        match self.smbus_op(SmbusRw::READ, addr, 0, SmbusSize::BYTE, SmbusData::NONE)? {
+            SmbusData::BYTE(value) => Ok(value),
+            response => Err(I2cError::IO(
+                cglue::EPROTO,
+                format!("unexpected smbus response:{:?}", response),
+            )),
         }
+    }
+
+    // send byte without register, value travels in the command field
+    pub fn send_byte(&self, addr: u32, value: u8) -> Result<(), I2cError> {
+        self.smbus_op(SmbusRw::WRITE, addr, value, SmbusSize::BYTE, SmbusData::NONE)?;
+        Ok(())
+    }
+
+    // write a word to reg and read back the device word answer in the same transaction
+    pub fn process_call(&self, addr: u32, reg: u8, value: u16) -> Result<u16, I2cError> {
+        match self.smbus_op(SmbusRw::WRITE, addr, reg, SmbusSize::PROC_CALL, SmbusData::WORD(value))? {
+            SmbusData::WORD(value) => Ok(value),
+            response => Err(I2cError::IO(
+                cglue::EPROTO,
+                format!("unexpected smbus response:{:?}", response),
+            )),
+        }
+    }
+
+    // SMBus 2.0 block write/read, request and answer are limited to 32 bytes
+    pub fn block_process_call(&self, addr: u32, reg: u8, data: &[u8]) -> Result<Vec<u8>, I2cError> {
+        if data.is_empty() || data.len() > I2C_SMBUS_BLOCK_MAX {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{} register:{} len:{} should be 1-{}",
+                addr, reg, data.len(), I2C_SMBUS_BLOCK_MAX
+            )));
+        }
+        let request = SmbusData::BLOCK(data.to_vec());
+        match self.smbus_op(SmbusRw::WRITE, addr, reg, SmbusSize::BLOCK_PROC_CALL, request)? {
+            SmbusData::BLOCK(values) => Ok(values),
+            response => Err(I2cError::IO(
+                cglue::EPROTO,
+                format!("unexpected smbus response:{:?}", response),
+            )),
+        }
+    }
+
+    // register pointer write followed by a repeated start read of len bytes
+    pub fn read_at(&self, addr: u32, reg: u16, len: usize) -> Result<Vec<u8>, I2cError> {
+        self.check_addr(addr)?;
+        let pointer = self.reg_pointer(addr, reg)?;
+        let mut msgs = [I2cMsg::write(addr as u16, &pointer), I2cMsg::read(addr as u16, len)];
+        if self.get_addr_cfg(addr).mode == I2cAddrMode::BIT10 {
+            msgs = msgs.map(|msg| msg.ten_bit());
+        }
+        let mut segments = self
+            .transfer_traced(&msgs, Some(reg))
+            .map_err(|error| error.context(format!("addr:{:#02x} register:{:#04x} read", addr, reg)))?;
+        Ok(segments.pop().unwrap_or_default())
+    }
+
+    // register pointer and data sent within one write segment
+    pub fn write_at(&self, addr: u32, reg: u16, data: &[u8]) -> Result<(), I2cError> {
+        self.check_addr(addr)?;
+        let mut buffer = self.reg_pointer(addr, reg)?;
+        buffer.extend_from_slice(data);
+        let mut msg = I2cMsg::write(addr as u16, &buffer);
+        if self.get_addr_cfg(addr).mode == I2cAddrMode::BIT10 {
+            msg = msg.ten_bit();
+        }
+        self.transfer_traced(&[msg], Some(reg))
+            .map_err(|error| error.context(format!("addr:{:#02x} register:{:#04x} write", addr, reg)))?;
+        Ok(())
+    }
+
+    // combined I2C_RDWR transaction, segments are chained with repeated start
+    // unless flagged no_start. Returns read segments data in request order.
+    pub fn transfer(&self, msgs: &[I2cMsg]) -> Result<Vec<Vec<u8>>, I2cError> {
+        self.transfer_traced(msgs, None)
+    }
 
-        match I2cHandle::mk_write(fd, reg, data) {
-            Err(error) => afb_error!(
-                "i2c-write-data",
-                "addr:{} register:{} error:{}", addr, reg, error
-            ),
-            Ok(value) => Ok(value),
+    // one trace event per transaction, addressed by its first segment, value holds
+    // read segments on success else written bytes
+    fn transfer_traced(&self, msgs: &[I2cMsg], reg: Option<u16>) -> Result<Vec<Vec<u8>>, I2cError> {
+        let start = Instant::now();
+        let result = self.transfer_locked(msgs);
+        if let Some(first) = msgs.first() {
+            let rw = if msgs.iter().any(|msg| msg.is_read()) {
+                SmbusRw::READ
+            } else {
+                SmbusRw::WRITE
+            };
+            let value = || match &result {
+                Ok(segments) if rw == SmbusRw::READ => SmbusData::BLOCK(segments.concat()),
+                _ => SmbusData::BLOCK(
+                    msgs.iter()
+                        .filter(|msg| !msg.is_read())
+                        .flat_map(|msg| msg.buf.iter().copied())
+                        .collect(),
+                ),
+            };
+            let kind = I2cTraceKind::I2C_RDWR(msgs.len());
+            self.trace(first.addr as u32, reg, rw, kind, start, value, result.as_ref().err());
         }
+        result
+    }
+
+    fn transfer_locked(&self, msgs: &[I2cMsg]) -> Result<Vec<Vec<u8>>, I2cError> {
+        if msgs.is_empty() || msgs.len() > I2C_RDWR_IOCTL_MAX_MSGS {
+            return Err(I2cError::INVALID_ARG(format!(
+                "invalid msgs count:{} should be 1-{}",
+                msgs.len(), I2C_RDWR_IOCTL_MAX_MSGS
+            )));
+        }
+        self.check_func(I2cFunc::I2C)?;
+
+        for msg in msgs {
+            let allow_reserved = self.get_addr_cfg(msg.addr as u32).allow_reserved;
+            if msg.flags & I2C_M_TEN != 0 {
+                self.check_func(I2cFunc::TEN_BIT_ADDR)?;
+                if msg.addr > 0x3FF {
+                    return Err(I2cError::INVALID_ARG(format!(
+                        "invalid 10bit addr={:#03x} flags:{:#04x}",
+                        msg.addr, msg.flags
+                    )));
+                }
+            } else if msg.addr > 0x7F
+                || (i2c_addr_reserved(msg.addr as u32) && !allow_reserved)
+            {
+                return Err(I2cError::INVALID_ARG(format!(
+                    "invalid or reserved addr={:#02x} flags:{:#04x}",
+                    msg.addr, msg.flags
+                )));
+            }
+            if msg.flags & I2C_M_NOSTART != 0 {
+                self.check_func(I2cFunc::NOSTART)?;
+            }
+            if msg.flags & I2C_M_IGNORE_NAK != 0 {
+                self.check_func(I2cFunc::PROTOCOL_MANGLING)?;
+            }
+            if msg.buf.len() > I2C_MSG_MAX_LEN || (msg.is_read() && msg.buf.is_empty()) {
+                return Err(I2cError::INVALID_ARG(format!(
+                    "addr={:#02x} invalid len:{}",
+                    msg.addr, msg.buf.len(
+                ))));
+            }
+        }
+
+        self.check_recovery()?;
+        let mut segments = msgs.to_vec();
+        let mut status = self.lock_backend().transfer(&mut segments);
+        if self.check_fatal(&status) {
+            segments = msgs.to_vec();
+            status = self.lock_backend().transfer(&mut segments);
+            self.check_fatal(&status);
+        }
+        if let Err(error) = status {
+            return Err(error.context(format!("bus:{} msgs:{}", self.get_name(), msgs.len())));
+        }
+
+        Ok(segments
+            .into_iter()
+            .filter(|msg| msg.is_read())
+            .map(|msg| msg.buf)
+            .collect())
     }
 }
 
-impl I2cDataCmd<u8> for I2cHandle {
-    fn mk_read(fd: i32, register: u8) -> Result<u8, String> {
-        let res = unsafe { cglue::i2c_smbus_read_byte_data(fd, register) };
-        println!("i2c_smbus_read_byte_data register:{:#02x} data:{:#02x}", register, res);
-        if res < 0 {
-            return Err(get_perror());
+impl<B: I2cBackend> I2cDataCmd<u8> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::BYTE_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<u8, I2cError> {
+        let res = self.smbus_xfer(SmbusRw::READ, register, SmbusSize::BYTE_DATA, SmbusData::NONE)?;
+        match res {
+            SmbusData::BYTE(data) => Ok(data),
+            _ => Err(I2cError::IO(cglue::EPROTO, format!("unexpected smbus response:{:?}", res))),
         }
-        Ok((res & 0xFF) as u8)
     }
 
-    fn mk_write(fd: i32, register: u8, data: u8) -> Result<(), String> {
-        let res = unsafe { cglue::i2c_smbus_write_byte_data(fd, register, data) };
-        println!("i2c_smbus_write_byte_data register:{:#02x} set:{:#02x} status:{}", register, data, res);
-        if res < 0 {
-            return Err(get_perror());
+    fn mk_write(&self, register: u8, data: u8) -> Result<(), I2cError> {
+        self.smbus_xfer(SmbusRw::WRITE, register, SmbusSize::BYTE_DATA, SmbusData::BYTE(data))?;
+        Ok(())
+    }
+}
+
+impl<B: I2cBackend> I2cDataCmd<u16> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::WORD_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<u16, I2cError> {
+        let res = self.smbus_xfer(SmbusRw::READ, register, SmbusSize::WORD_DATA, SmbusData::NONE)?;
+        match res {
+            SmbusData::WORD(data) => Ok(data),
+            _ => Err(I2cError::IO(cglue::EPROTO, format!("unexpected smbus response:{:?}", res))),
         }
+    }
+
+    fn mk_write(&self, register: u8, data: u16) -> Result<(), I2cError> {
+        self.smbus_xfer(SmbusRw::WRITE, register, SmbusSize::WORD_DATA, SmbusData::WORD(data))?;
         Ok(())
     }
 }
 
-impl I2cDataCmd<u16> for I2cHandle {
-    fn mk_read(fd: i32, register: u8) -> Result<u16, String> {
-        let res = unsafe { cglue::i2c_smbus_read_word_data(fd, register) };
-        println!("i2c_smbus_read_byte_data register:{:#02x} data:{:#04x}", register, res);
-        if res < 0 {
-            return Err(get_perror());
+impl<B: I2cBackend> I2cDataCmd<Vec<u8>> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::BLOCK_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<Vec<u8>, I2cError> {
+        let res = self.smbus_xfer(SmbusRw::READ, register, SmbusSize::BLOCK_DATA, SmbusData::NONE)?;
+        match res {
+            SmbusData::BLOCK(data) => Ok(data),
+            _ => Err(I2cError::IO(cglue::EPROTO, format!("unexpected smbus response:{:?}", res))),
         }
-        Ok((res & 0xFFFF) as u16)
     }
 
-    fn mk_write(fd: i32, register: u8, data: u16) -> Result<(), String> {
-        let res = unsafe { cglue::i2c_smbus_write_word_data(fd, register, data) };
-        println!("i2c_smbus_write_byte_data register:{:#02x} set:{:#02x} status:{}", register, data, res);
-        if res < 0 {
-            return Err(get_perror());
+    fn mk_write(&self, register: u8, data: Vec<u8>) -> Result<(), I2cError> {
+        self.smbus_xfer(SmbusRw::WRITE, register, SmbusSize::BLOCK_DATA, SmbusData::BLOCK(data))?;
+        Ok(())
+    }
+}
+
+impl<B: I2cBackend> I2cDataCmd<i8> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::BYTE_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<i8, I2cError> {
+        let data: u8 = self.mk_read(register)?;
+        Ok(data as i8)
+    }
+
+    fn mk_write(&self, register: u8, data: i8) -> Result<(), I2cError> {
+        self.mk_write(register, data as u8)
+    }
+}
+
+impl<B: I2cBackend> I2cDataCmd<i16> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::WORD_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<i16, I2cError> {
+        let data: u16 = self.mk_read(register)?;
+        Ok(data as i16)
+    }
+
+    fn mk_write(&self, register: u8, data: i16) -> Result<(), I2cError> {
+        self.mk_write(register, data as u16)
+    }
+}
+
+// 4 consecutive registers read as one I2C block, first register is the low byte
+impl<B: I2cBackend> I2cDataCmd<u32> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::I2C_BLOCK_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<u32, I2cError> {
+        let request = SmbusData::BLOCK(vec![0; 4]);
+        let res = self.smbus_xfer(SmbusRw::READ, register, SmbusSize::I2C_BLOCK_DATA, request)?;
+        match res {
+            SmbusData::BLOCK(data) if data.len() == 4 => Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+            _ => Err(I2cError::IO(cglue::EPROTO, format!("unexpected smbus response:{:?}", res))),
         }
+    }
+
+    fn mk_write(&self, register: u8, data: u32) -> Result<(), I2cError> {
+        self.smbus_xfer(SmbusRw::WRITE, register, SmbusSize::I2C_BLOCK_DATA, data.into())?;
         Ok(())
     }
 }
 
+impl<B: I2cBackend> I2cDataCmd<i32> for B {
+    const SMBUS_SIZE: SmbusSize = SmbusSize::I2C_BLOCK_DATA;
+
+    fn mk_read(&self, register: u8) -> Result<i32, I2cError> {
+        let data: u32 = self.mk_read(register)?;
+        Ok(data as i32)
+    }
+
+    fn mk_write(&self, register: u8, data: i32) -> Result<(), I2cError> {
+        self.mk_write(register, data as u32)
+    }
+}
+
+// register oriented data access, implemented on top of any bus backend
 pub trait I2cDataCmd<T> {
-    fn mk_read(fd: i32, register: u8) -> Result<T, String>;
-    fn mk_write(fd: i32, register: u8, data: T) -> Result<(), String>;
+    // SMBus transaction used, checked against adapter functionality
+    const SMBUS_SIZE: SmbusSize;
+
+    fn mk_read(&self, register: u8) -> Result<T, I2cError>;
+    fn mk_write(&self, register: u8, data: T) -> Result<(), I2cError>;
 }
@@ -0,0 +1,193 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::prelude::*;
+
+// device byte order, SMBus words and 32bit blocks are little-endian on the wire
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cEndian {
+    #[default]
+    LITTLE,
+    BIG,
+}
+
+// numeric register layout, 32bit types span 4 consecutive registers (I2C block)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum I2cDataType {
+    #[default]
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+impl I2cDataType {
+    // number of bytes on the wire
+    pub fn get_size(&self) -> u8 {
+        match self {
+            I2cDataType::U8 | I2cDataType::I8 => 1,
+            I2cDataType::U16 | I2cDataType::I16 => 2,
+            I2cDataType::U32 | I2cDataType::I32 => 4,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, I2cDataType::I8 | I2cDataType::I16 | I2cDataType::I32)
+    }
+
+    pub fn get_range(&self) -> (i64, i64) {
+        match self {
+            I2cDataType::U8 => (u8::MIN as i64, u8::MAX as i64),
+            I2cDataType::I8 => (i8::MIN as i64, i8::MAX as i64),
+            I2cDataType::U16 => (u16::MIN as i64, u16::MAX as i64),
+            I2cDataType::I16 => (i16::MIN as i64, i16::MAX as i64),
+            I2cDataType::U32 => (u32::MIN as i64, u32::MAX as i64),
+            I2cDataType::I32 => (i32::MIN as i64, i32::MAX as i64),
+        }
+    }
+
+    // raw register bits (eg: hexa 0xFFFE) to value, signed types are two's-complement
+    pub fn from_raw(&self, raw: u32) -> i64 {
+        match self {
+            I2cDataType::U8 => raw as u8 as i64,
+            I2cDataType::I8 => raw as u8 as i8 as i64,
+            I2cDataType::U16 => raw as u16 as i64,
+            I2cDataType::I16 => raw as u16 as i16 as i64,
+            I2cDataType::U32 => raw as i64,
+            I2cDataType::I32 => raw as i32 as i64,
+        }
+    }
+
+    // raw bits should fit type width, 0x1FF is not an i8 register content
+    pub fn check_raw(&self, raw: u32) -> Result<(), I2cError> {
+        let bits = self.get_size() as u32 * 8;
+        if bits < 32 && raw >> bits != 0 {
+            return Err(I2cError::INVALID_ARG(format!(
+                "raw:{:#x} does not fit {:?} ({} bytes)",
+                raw, self, self.get_size()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_value(&self, value: i64) -> Result<(), I2cError> {
+        let (min, max) = self.get_range();
+        if value < min || value > max {
+            return Err(I2cError::INVALID_ARG(format!(
+                "value:{} out of {:?} range {}..{}",
+                value, self, min, max
+            )));
+        }
+        Ok(())
+    }
+
+    // value to SmbusData in wire order, as written by I2cDataCmd for the same type
+    pub fn encode(&self, value: i64, endian: I2cEndian) -> Result<SmbusData, I2cError> {
+        self.check_value(value)?;
+        let big = endian == I2cEndian::BIG;
+        let data = match self {
+            I2cDataType::U8 | I2cDataType::I8 => SmbusData::BYTE(value as u8),
+            I2cDataType::U16 | I2cDataType::I16 => {
+                let value = value as u16;
+                SmbusData::WORD(if big { value.swap_bytes() } else { value })
+            }
+            I2cDataType::U32 | I2cDataType::I32 => {
+                let value = value as u32;
+                SmbusData::from(if big { value.swap_bytes() } else { value })
+            }
+        };
+        Ok(data)
+    }
+
+    // SmbusData in wire order back to value, reverse of encode
+    pub fn decode(&self, data: &SmbusData, endian: I2cEndian) -> Result<i64, I2cError> {
+        let mut bytes = data.to_bytes();
+        if bytes.len() != self.get_size() as usize {
+            return Err(I2cError::IO(
+                cglue::EPROTO,
+                format!("{:?} unexpected data:{:?}", self, data),
+            ));
+        }
+        if endian == I2cEndian::BIG {
+            bytes.reverse();
+        }
+        let mut raw = [0u8; 4];
+        raw[..bytes.len()].copy_from_slice(&bytes);
+        Ok(self.from_raw(u32::from_le_bytes(raw)))
+    }
+}
+
+impl<B: I2cBackend> I2cHandle<B> {
+    // read a numeric register with the transaction matching its size, then decode it
+    pub fn read_value(
+        &self,
+        addr: u32,
+        reg: u8,
+        dtype: I2cDataType,
+        endian: I2cEndian,
+    ) -> Result<i64, I2cError> {
+        let data = match dtype.get_size() {
+            1 => SmbusData::BYTE(self.read::<u8>(addr, reg)?),
+            2 => SmbusData::WORD(self.read::<u16>(addr, reg)?),
+            _ => SmbusData::from(self.read::<u32>(addr, reg)?),
+        };
+        dtype.decode(&data, endian)
+    }
+
+    // encode value following register type and byte order, out of range values are rejected
+    pub fn write_value(
+        &self,
+        addr: u32,
+        reg: u8,
+        dtype: I2cDataType,
+        endian: I2cEndian,
+        value: i64,
+    ) -> Result<(), I2cError> {
+        match dtype.encode(value, endian)? {
+            SmbusData::BYTE(data) => self.write(addr, reg, data),
+            SmbusData::WORD(data) => self.write(addr, reg, data),
+            data => {
+                let bytes = data.to_bytes();
+                self.write(addr, reg, u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_bits_fit_type() {
+        assert_eq!(I2cDataType::I16.from_raw(0xFFF6), -10);
+        assert!(I2cDataType::I8.check_raw(0xFF).is_ok());
+        assert!(I2cDataType::I8.check_raw(0x1FF).is_err());
+        assert!(I2cDataType::U16.check_raw(0x12345).is_err());
+        assert!(I2cDataType::U32.check_raw(0xFFFF_FFFF).is_ok());
+    }
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let data = I2cDataType::I16.encode(-10, I2cEndian::BIG).unwrap();
+        assert_eq!(data, SmbusData::WORD(0xF6FF));
+        assert_eq!(I2cDataType::I16.decode(&data, I2cEndian::BIG).unwrap(), -10);
+
+        let data = I2cDataType::U32.encode(0x01020304, I2cEndian::LITTLE).unwrap();
+        assert_eq!(data, SmbusData::BLOCK(vec![0x04, 0x03, 0x02, 0x01]));
+        assert_eq!(I2cDataType::U32.decode(&data, I2cEndian::LITTLE).unwrap(), 0x01020304);
+
+        assert!(I2cDataType::U8.encode(256, I2cEndian::LITTLE).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.kernel.org/i2c/dev-interface.html
+ *  https://www.kernel.org/doc/Documentation/i2c/dev-interface
+ *
+ */
+
+use crate::prelude::*;
+#[cfg(feature = "afbv4")]
+use afbv4::prelude::*;
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::mem;
+use std::os::fd::{FromRawFd, IntoRawFd};
+use std::os::raw;
+use std::time::Duration;
+
+// kernel /dev/i2c-N character device backend
+pub struct I2cDevBus {
+    name: String,
+    raw_fd: Cell<i32>,
+    sysfs: I2cSysfs,
+}
+
+impl I2cDevBus {
+    pub fn new(i2cbus: &str) -> Result<I2cDevBus, I2cError> {
+        if i2cbus.is_empty() || i2cbus.contains('\0') {
+            return Err(I2cError::INVALID_ARG(format!("invalid device name:{:?}", i2cbus)));
+        }
+
+        Ok(I2cDevBus {
+            name: i2cbus.to_string(),
+            raw_fd: Cell::new(-1),
+            sysfs: I2cSysfs::default(),
+        })
+    }
+
+    // resolve adapter name (eg: 'i915 gmbus dpb') to its /dev/i2c-N node
+    pub fn from_adapter(name: &str, sysfs: &I2cSysfs) -> Result<I2cDevBus, I2cError> {
+        let adapter = sysfs.find_adapter(name)?;
+        #[cfg(feature = "afbv4")]
+        afb_log_msg!(
+            Notice,
+            None,
+            "adapter:'{}' found as {}",
+            adapter.name,
+            adapter.devname
+        );
+        Ok(I2cDevBus::new(adapter.devname.as_str())?.set_sysfs(sysfs.clone()))
+    }
+
+    // alternate sysfs root used for adapter/driver lookups
+    pub fn set_sysfs(mut self, sysfs: I2cSysfs) -> Self {
+        self.sysfs = sysfs;
+        self
+    }
+
+    pub fn get_sysfs(&self) -> &I2cSysfs {
+        &self.sysfs
+    }
+
+    pub fn get_fd(&self) -> i32 {
+        self.raw_fd.get()
+    }
+
+    // adapter number from /dev/i2c-N device name
+    pub fn get_busnum(&self) -> Option<u32> {
+        let (_, busnum) = self.name.rsplit_once("i2c-")?;
+        busnum.parse::<u32>().ok()
+    }
+}
+
+impl I2cBackend for I2cDevBus {
+    fn open(&self) -> Result<(), I2cError> {
+        // open tty i2cbus, fd ownership is kept by the cell until close
+        let raw_fd = match OpenOptions::new().read(true).write(true).open(&self.name) {
+            Err(error) => {
+                let errno = error.raw_os_error().unwrap_or(cglue::ENODEV);
+                return Err(I2cError::from_errno(errno, format!("dev:{:?} open error:{}", self.name, error)));
+            }
+            Ok(file) => file.into_raw_fd(),
+        };
+
+        // update fd cell within immutable handle
+        self.raw_fd.set(raw_fd);
+        #[cfg(feature = "afbv4")]
+        afb_log_msg!(Debug, None, "Open port={:?}", self.name);
+        Ok(())
+    }
+
+    fn close(&self) {
+        let raw_fd = self.raw_fd.replace(-1);
+        if raw_fd >= 0 {
+            drop(unsafe { File::from_raw_fd(raw_fd) });
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn get_funcs(&self) -> Result<I2cFuncs, I2cError> {
+        let mut funcs: raw::c_ulong = 0;
+        if unsafe {
+            cglue::ioctl(
+                self.raw_fd.get(),
+                cglue::BUS_I2C_FUNCS,
+                &mut funcs as *mut raw::c_ulong,
+            )
+        } < 0
+        {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(I2cFuncs::new(funcs as u64))
+    }
+
+    // I2C_TIMEOUT unit is 10ms
+    fn set_timeout(&self, timeout: Duration) -> Result<(), I2cError> {
+        let jiffies = timeout.as_millis().div_ceil(10) as raw::c_ulong;
+        if unsafe { cglue::ioctl(self.raw_fd.get(), cglue::BUS_I2C_TIMEOUT, jiffies) } < 0 {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_retries(&self, retries: u32) -> Result<(), I2cError> {
+        if unsafe { cglue::ioctl(self.raw_fd.get(), cglue::BUS_I2C_RETRIES, retries as raw::c_ulong) } < 0 {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_tenbit(&self, enable: bool) -> Result<(), I2cError> {
+        if unsafe { cglue::ioctl(self.raw_fd.get(), cglue::BUS_I2C_TENBIT, enable as raw::c_ulong) } < 0 {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_address(&self, addr: u32, force: bool) -> Result<(), I2cError> {
+        let request = if force {
+            cglue::BUS_I2C_SLAVE_FORCE
+        } else {
+            cglue::BUS_I2C_SLAVE
+        };
+        if unsafe { cglue::ioctl(self.raw_fd.get(), request, addr as raw::c_ulong) } < 0 {
+            if get_errno() == cglue::EBUSY {
+                return Err(I2cError::ADDR_BUSY(format!("addr:{:#02x} claimed by a kernel driver", addr)));
+            }
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn get_driver(&self, addr: u32) -> Option<String> {
+        self.sysfs.get_driver(self.get_busnum()?, addr)
+    }
+
+    fn set_pec(&self, enable: bool) -> Result<(), I2cError> {
+        if unsafe { cglue::ioctl(self.raw_fd.get(), cglue::BUS_I2C_PEC, enable as raw::c_ulong) } < 0 {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn smbus_xfer(
+        &self,
+        rw: SmbusRw,
+        command: u8,
+        size: SmbusSize,
+        data: SmbusData,
+    ) -> Result<SmbusData, I2cError> {
+        let read_write = match rw {
+            SmbusRw::READ => cglue::BUS_I2C_SMBUS_READ,
+            SmbusRw::WRITE => cglue::BUS_I2C_SMBUS_WRITE,
+        };
+
+        let ksize = match size {
+            SmbusSize::QUICK => cglue::BUS_I2C_SMBUS_QUICK,
+            SmbusSize::BYTE => cglue::BUS_I2C_SMBUS_BYTE,
+            SmbusSize::BYTE_DATA => cglue::BUS_I2C_SMBUS_BYTE_DATA,
+            SmbusSize::WORD_DATA => cglue::BUS_I2C_SMBUS_WORD_DATA,
+            SmbusSize::PROC_CALL => cglue::BUS_I2C_SMBUS_PROC_CALL,
+            SmbusSize::BLOCK_DATA => cglue::BUS_I2C_SMBUS_BLOCK_DATA,
+            SmbusSize::BLOCK_PROC_CALL => cglue::BUS_I2C_SMBUS_BLOCK_PROC_CALL,
+            SmbusSize::I2C_BLOCK_DATA => cglue::BUS_I2C_SMBUS_I2C_BLOCK_DATA,
+        };
+
+        // kernel union: block[0] holds length, payload follows
+        let mut kdata: cglue::i2c_smbus_data = unsafe { mem::zeroed() };
+        match &data {
+            SmbusData::NONE => {}
+            SmbusData::BYTE(value) => kdata.byte = *value,
+            SmbusData::WORD(value) => kdata.word = *value,
+            SmbusData::BLOCK(values) => {
+                if values.len() > I2C_SMBUS_BLOCK_MAX {
+                    return Err(I2cError::INVALID_ARG(format!(
+                        "block len:{} > max:{}",
+                        values.len(),
+                        I2C_SMBUS_BLOCK_MAX
+                    )));
+                }
+                let block = unsafe { &mut kdata.block };
+                block[0] = values.len() as u8;
+                block[1..values.len() + 1].copy_from_slice(values);
+            }
+        }
+
+        let res = unsafe {
+            cglue::i2c_smbus_access(
+                self.raw_fd.get(),
+                read_write,
+                command,
+                ksize,
+                &mut kdata as *mut cglue::i2c_smbus_data,
+            )
+        };
+        // EBADMSG is reported as PEC error
+        if res < 0 {
+            return Err(I2cError::last_os_error());
+        }
+
+        let response = match (rw, size) {
+            (SmbusRw::WRITE, SmbusSize::PROC_CALL) => SmbusData::WORD(unsafe { kdata.word }),
+            (SmbusRw::WRITE, SmbusSize::BLOCK_PROC_CALL)
+            | (SmbusRw::READ, SmbusSize::BLOCK_DATA)
+            | (SmbusRw::READ, SmbusSize::I2C_BLOCK_DATA) => {
+                let block = unsafe { &kdata.block };
+                let len = (block[0] as usize).min(I2C_SMBUS_BLOCK_MAX);
+                SmbusData::BLOCK(block[1..len + 1].to_vec())
+            }
+            (SmbusRw::WRITE, _) | (SmbusRw::READ, SmbusSize::QUICK) => SmbusData::NONE,
+            (SmbusRw::READ, SmbusSize::BYTE) | (SmbusRw::READ, SmbusSize::BYTE_DATA) => {
+                SmbusData::BYTE(unsafe { kdata.byte })
+            }
+            (SmbusRw::READ, _) => SmbusData::WORD(unsafe { kdata.word }),
+        };
+        Ok(response)
+    }
+
+    fn transfer(&self, msgs: &mut [I2cMsg]) -> Result<(), I2cError> {
+        let mut kmsgs: Vec<cglue::i2c_msg> = msgs
+            .iter_mut()
+            .map(|msg| cglue::i2c_msg {
+                addr: msg.addr,
+                flags: msg.flags,
+                len: msg.buf.len() as u16,
+                buf: msg.buf.as_mut_ptr(),
+            })
+            .collect();
+
+        let mut rdwr = cglue::i2c_rdwr_ioctl_data {
+            msgs: kmsgs.as_mut_ptr(),
+            nmsgs: kmsgs.len() as u32,
+        };
+
+        let res = unsafe {
+            cglue::ioctl(
+                self.raw_fd.get(),
+                cglue::BUS_I2C_RDWR,
+                &mut rdwr as *mut cglue::i2c_rdwr_ioctl_data,
+            )
+        };
+        if res < 0 {
+            return Err(I2cError::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.kernel.org/i2c/fault-codes.html
+ *
+ */
+
+use crate::prelude::*;
+use std::error;
+use std::fmt;
+
+// libi2c error model, kernel errno are mapped following i2c fault-codes.rst
+#[allow(non_camel_case_types)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum I2cError {
+    // ENXIO/EREMOTEIO: slave did not acknowledge its address or data
+    NO_ACK(String),
+    // ETIMEDOUT: adapter timeout (clock stretching, stuck bus)
+    TIMEOUT(String),
+    // EBUSY/ENOTCONN: bus held by another master
+    BUS_BUSY(String),
+    // EBUSY on I2C_SLAVE: address claimed by a kernel driver
+    ADDR_BUSY(String),
+    // EAGAIN: arbitration lost, transaction may be retried
+    ARBITRATION_LOST(String),
+    // EOPNOTSUPP/EPROTONOSUPPORT or missing I2C_FUNCS capability
+    UNSUPPORTED(String),
+    // EBADMSG: SMBus packet error checking mismatch
+    PEC(String),
+    // EINVAL or argument rejected before reaching the bus
+    INVALID_ARG(String),
+    // any other system error
+    IO(i32, String),
+}
+
+impl I2cError {
+    pub fn from_errno(errno: i32, info: String) -> I2cError {
+        match errno {
+            cglue::ENXIO | cglue::EREMOTEIO => I2cError::NO_ACK(info),
+            cglue::ETIMEDOUT => I2cError::TIMEOUT(info),
+            cglue::EBUSY | cglue::ENOTCONN => I2cError::BUS_BUSY(info),
+            cglue::EAGAIN => I2cError::ARBITRATION_LOST(info),
+            cglue::EOPNOTSUPP | cglue::EPROTONOSUPPORT => I2cError::UNSUPPORTED(info),
+            cglue::EBADMSG => I2cError::PEC(info),
+            cglue::EINVAL => I2cError::INVALID_ARG(info),
+            _ => I2cError::IO(errno, info),
+        }
+    }
+
+    // build from errno left by the last failing system call
+    pub fn last_os_error() -> I2cError {
+        I2cError::from_errno(get_errno(), get_perror())
+    }
+
+    // stable id, used as client visible error uid
+    pub fn get_uid(&self) -> &'static str {
+        match self {
+            I2cError::NO_ACK(_) => "i2c-no-ack",
+            I2cError::TIMEOUT(_) => "i2c-timeout",
+            I2cError::BUS_BUSY(_) => "i2c-bus-busy",
+            I2cError::ADDR_BUSY(_) => "i2c-addr-busy",
+            I2cError::ARBITRATION_LOST(_) => "i2c-arbitration-lost",
+            I2cError::UNSUPPORTED(_) => "i2c-unsupported",
+            I2cError::PEC(_) => "i2c-pec-error",
+            I2cError::INVALID_ARG(_) => "i2c-invalid-arg",
+            I2cError::IO(_, _) => "i2c-io-error",
+        }
+    }
+
+    pub fn get_info(&self) -> &str {
+        match self {
+            I2cError::NO_ACK(info)
+            | I2cError::TIMEOUT(info)
+            | I2cError::BUS_BUSY(info)
+            | I2cError::ADDR_BUSY(info)
+            | I2cError::ARBITRATION_LOST(info)
+            | I2cError::UNSUPPORTED(info)
+            | I2cError::PEC(info)
+            | I2cError::INVALID_ARG(info)
+            | I2cError::IO(_, info) => info.as_str(),
+        }
+    }
+
+    // kernel errno matching the error kind
+    pub fn get_errno(&self) -> i32 {
+        match self {
+            I2cError::NO_ACK(_) => cglue::ENXIO,
+            I2cError::TIMEOUT(_) => cglue::ETIMEDOUT,
+            I2cError::BUS_BUSY(_) | I2cError::ADDR_BUSY(_) => cglue::EBUSY,
+            I2cError::ARBITRATION_LOST(_) => cglue::EAGAIN,
+            I2cError::UNSUPPORTED(_) => cglue::EOPNOTSUPP,
+            I2cError::PEC(_) => cglue::EBADMSG,
+            I2cError::INVALID_ARG(_) => cglue::EINVAL,
+            I2cError::IO(errno, _) => *errno,
+        }
+    }
+
+    // adapter or device node is gone (unplugged, driver unbound), bus should be reopened
+    // EIO is not fatal on its own, many adapters report any failed transfer with it
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, I2cError::IO(cglue::ENODEV | cglue::EBADF, _))
+    }
+
+    // generic adapter failure, only a run of them triggers a reopen (see I2cRecovery)
+    pub fn is_eio(&self) -> bool {
+        matches!(self, I2cError::IO(cglue::EIO, _))
+    }
+
+    // keep error kind, prefix info with caller context (addr, register, ...)
+    pub fn context(self, context: String) -> I2cError {
+        let info = format!("{} {}", context, self.get_info());
+        match self {
+            I2cError::NO_ACK(_) => I2cError::NO_ACK(info),
+            I2cError::TIMEOUT(_) => I2cError::TIMEOUT(info),
+            I2cError::BUS_BUSY(_) => I2cError::BUS_BUSY(info),
+            I2cError::ADDR_BUSY(_) => I2cError::ADDR_BUSY(info),
+            I2cError::ARBITRATION_LOST(_) => I2cError::ARBITRATION_LOST(info),
+            I2cError::UNSUPPORTED(_) => I2cError::UNSUPPORTED(info),
+            I2cError::PEC(_) => I2cError::PEC(info),
+            I2cError::INVALID_ARG(_) => I2cError::INVALID_ARG(info),
+            I2cError::IO(errno, _) => I2cError::IO(errno, info),
+        }
+    }
+}
+
+impl fmt::Display for I2cError {
+    fn fmt(&self, format: &mut fmt::Formatter) -> fmt::Result {
+        write!(format, "{}:{}", self.get_uid(), self.get_info())
+    }
+}
+
+impl error::Error for I2cError {}
+
+#[cfg(feature = "afbv4")]
+impl From<I2cError> for afbv4::prelude::AfbError {
+    #[track_caller]
+    fn from(error: I2cError) -> Self {
+        use afbv4::prelude::*;
+        let result: Result<(), AfbError> =
+            afb_error!(error.get_uid(), "{}", error.get_info());
+        match result {
+            Err(afb_error) => afb_error,
+            Ok(()) => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.kernel.org/i2c/functionality.html
+ *
+ */
+
+use crate::prelude::*;
+use std::fmt;
+
+// adapter functionality as reported by I2C_FUNCS (linux/i2c.h)
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum I2cFunc {
+    I2C,
+    TEN_BIT_ADDR,
+    PROTOCOL_MANGLING,
+    SMBUS_PEC,
+    NOSTART,
+    SMBUS_BLOCK_PROC_CALL,
+    SMBUS_QUICK,
+    SMBUS_READ_BYTE,
+    SMBUS_WRITE_BYTE,
+    SMBUS_READ_BYTE_DATA,
+    SMBUS_WRITE_BYTE_DATA,
+    SMBUS_READ_WORD_DATA,
+    SMBUS_WRITE_WORD_DATA,
+    SMBUS_PROC_CALL,
+    SMBUS_READ_BLOCK_DATA,
+    SMBUS_WRITE_BLOCK_DATA,
+    SMBUS_READ_I2C_BLOCK,
+    SMBUS_WRITE_I2C_BLOCK,
+    SMBUS_HOST_NOTIFY,
+}
+
+const I2C_FUNC_ALL: [I2cFunc; 19] = [
+    I2cFunc::I2C,
+    I2cFunc::TEN_BIT_ADDR,
+    I2cFunc::PROTOCOL_MANGLING,
+    I2cFunc::SMBUS_PEC,
+    I2cFunc::NOSTART,
+    I2cFunc::SMBUS_BLOCK_PROC_CALL,
+    I2cFunc::SMBUS_QUICK,
+    I2cFunc::SMBUS_READ_BYTE,
+    I2cFunc::SMBUS_WRITE_BYTE,
+    I2cFunc::SMBUS_READ_BYTE_DATA,
+    I2cFunc::SMBUS_WRITE_BYTE_DATA,
+    I2cFunc::SMBUS_READ_WORD_DATA,
+    I2cFunc::SMBUS_WRITE_WORD_DATA,
+    I2cFunc::SMBUS_PROC_CALL,
+    I2cFunc::SMBUS_READ_BLOCK_DATA,
+    I2cFunc::SMBUS_WRITE_BLOCK_DATA,
+    I2cFunc::SMBUS_READ_I2C_BLOCK,
+    I2cFunc::SMBUS_WRITE_I2C_BLOCK,
+    I2cFunc::SMBUS_HOST_NOTIFY,
+];
+
+impl I2cFunc {
+    pub fn get_mask(&self) -> u64 {
+        match self {
+            I2cFunc::I2C => 0x00000001,
+            I2cFunc::TEN_BIT_ADDR => 0x00000002,
+            I2cFunc::PROTOCOL_MANGLING => 0x00000004,
+            I2cFunc::SMBUS_PEC => 0x00000008,
+            I2cFunc::NOSTART => 0x00000010,
+            I2cFunc::SMBUS_BLOCK_PROC_CALL => 0x00008000,
+            I2cFunc::SMBUS_QUICK => 0x00010000,
+            I2cFunc::SMBUS_READ_BYTE => 0x00020000,
+            I2cFunc::SMBUS_WRITE_BYTE => 0x00040000,
+            I2cFunc::SMBUS_READ_BYTE_DATA => 0x00080000,
+            I2cFunc::SMBUS_WRITE_BYTE_DATA => 0x00100000,
+            I2cFunc::SMBUS_READ_WORD_DATA => 0x00200000,
+            I2cFunc::SMBUS_WRITE_WORD_DATA => 0x00400000,
+            I2cFunc::SMBUS_PROC_CALL => 0x00800000,
+            I2cFunc::SMBUS_READ_BLOCK_DATA => 0x01000000,
+            I2cFunc::SMBUS_WRITE_BLOCK_DATA => 0x02000000,
+            I2cFunc::SMBUS_READ_I2C_BLOCK => 0x04000000,
+            I2cFunc::SMBUS_WRITE_I2C_BLOCK => 0x08000000,
+            I2cFunc::SMBUS_HOST_NOTIFY => 0x10000000,
+        }
+    }
+
+    // human readable label, aligned on i2c-tools error messages
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            I2cFunc::I2C => "I2C",
+            I2cFunc::TEN_BIT_ADDR => "10-bit addressing",
+            I2cFunc::PROTOCOL_MANGLING => "I2C protocol mangling",
+            I2cFunc::SMBUS_PEC => "SMBus PEC",
+            I2cFunc::NOSTART => "I2C no start",
+            I2cFunc::SMBUS_BLOCK_PROC_CALL => "SMBus block process call",
+            I2cFunc::SMBUS_QUICK => "SMBus quick command",
+            I2cFunc::SMBUS_READ_BYTE => "SMBus receive byte",
+            I2cFunc::SMBUS_WRITE_BYTE => "SMBus send byte",
+            I2cFunc::SMBUS_READ_BYTE_DATA => "SMBus read byte",
+            I2cFunc::SMBUS_WRITE_BYTE_DATA => "SMBus write byte",
+            I2cFunc::SMBUS_READ_WORD_DATA => "SMBus read word",
+            I2cFunc::SMBUS_WRITE_WORD_DATA => "SMBus write word",
+            I2cFunc::SMBUS_PROC_CALL => "SMBus process call",
+            I2cFunc::SMBUS_READ_BLOCK_DATA => "SMBus block read",
+            I2cFunc::SMBUS_WRITE_BLOCK_DATA => "SMBus block write",
+            I2cFunc::SMBUS_READ_I2C_BLOCK => "I2C block read",
+            I2cFunc::SMBUS_WRITE_I2C_BLOCK => "I2C block write",
+            I2cFunc::SMBUS_HOST_NOTIFY => "SMBus host notify",
+        }
+    }
+
+    // functionality required by a given SMBus transaction
+    pub fn from_smbus(rw: SmbusRw, size: SmbusSize) -> I2cFunc {
+        match (rw, size) {
+            (_, SmbusSize::QUICK) => I2cFunc::SMBUS_QUICK,
+            (SmbusRw::READ, SmbusSize::BYTE) => I2cFunc::SMBUS_READ_BYTE,
+            (SmbusRw::WRITE, SmbusSize::BYTE) => I2cFunc::SMBUS_WRITE_BYTE,
+            (SmbusRw::READ, SmbusSize::BYTE_DATA) => I2cFunc::SMBUS_READ_BYTE_DATA,
+            (SmbusRw::WRITE, SmbusSize::BYTE_DATA) => I2cFunc::SMBUS_WRITE_BYTE_DATA,
+            (SmbusRw::READ, SmbusSize::WORD_DATA) => I2cFunc::SMBUS_READ_WORD_DATA,
+            (SmbusRw::WRITE, SmbusSize::WORD_DATA) => I2cFunc::SMBUS_WRITE_WORD_DATA,
+            (_, SmbusSize::PROC_CALL) => I2cFunc::SMBUS_PROC_CALL,
+            (_, SmbusSize::BLOCK_PROC_CALL) => I2cFunc::SMBUS_BLOCK_PROC_CALL,
+            (SmbusRw::READ, SmbusSize::BLOCK_DATA) => I2cFunc::SMBUS_READ_BLOCK_DATA,
+            (SmbusRw::WRITE, SmbusSize::BLOCK_DATA) => I2cFunc::SMBUS_WRITE_BLOCK_DATA,
+            (SmbusRw::READ, SmbusSize::I2C_BLOCK_DATA) => I2cFunc::SMBUS_READ_I2C_BLOCK,
+            (SmbusRw::WRITE, SmbusSize::I2C_BLOCK_DATA) => I2cFunc::SMBUS_WRITE_I2C_BLOCK,
+        }
+    }
+}
+
+impl fmt::Display for I2cFunc {
+    fn fmt(&self, format: &mut fmt::Formatter) -> fmt::Result {
+        write!(format, "{}", self.as_str())
+    }
+}
+
+// typed adapter capability set
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct I2cFuncs {
+    mask: u64,
+}
+
+impl I2cFuncs {
+    pub fn new(mask: u64) -> I2cFuncs {
+        I2cFuncs { mask }
+    }
+
+    // every functionality but host notify, default for simulated bus
+    pub fn all() -> I2cFuncs {
+        let mask = I2C_FUNC_ALL
+            .iter()
+            .filter(|func| !matches!(func, I2cFunc::SMBUS_HOST_NOTIFY))
+            .fold(0, |mask, func| mask | func.get_mask());
+        I2cFuncs { mask }
+    }
+
+    pub fn get_mask(&self) -> u64 {
+        self.mask
+    }
+
+    pub fn has(&self, func: I2cFunc) -> bool {
+        self.mask & func.get_mask() != 0
+    }
+
+    pub fn get_list(&self) -> Vec<I2cFunc> {
+        I2C_FUNC_ALL
+            .iter()
+            .filter(|func| self.has(**func))
+            .copied()
+            .collect()
+    }
+}
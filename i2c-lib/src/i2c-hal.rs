@@ -0,0 +1,221 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.rs/embedded-hal/1.0.0/embedded_hal/i2c/index.html
+ *
+ */
+
+use crate::prelude::*;
+use embedded_hal::i2c::{self as hal, ErrorKind, NoAcknowledgeSource, Operation};
+use std::sync::Arc;
+
+impl hal::Error for I2cError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            I2cError::NO_ACK(_) => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            I2cError::ARBITRATION_LOST(_) => ErrorKind::ArbitrationLoss,
+            I2cError::BUS_BUSY(_) | I2cError::TIMEOUT(_) => ErrorKind::Bus,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+// embedded-hal view of one slave, drivers only reach the address the view was built for
+// and per-address options set on the handle still apply (TenBitAddress needs addr-mode 10bit)
+pub struct I2cHalDevice<B: I2cBackend = I2cDevBus> {
+    i2c: Arc<I2cHandle<B>>,
+    addr: u16,
+}
+
+impl<B: I2cBackend> I2cHalDevice<B> {
+    pub fn new(i2c: Arc<I2cHandle<B>>, addr: u16) -> Result<I2cHalDevice<B>, I2cError> {
+        i2c.check_func(I2cFunc::I2C)?;
+        i2c.check_addr(addr as u32)?;
+        Ok(I2cHalDevice { i2c, addr })
+    }
+
+    pub fn get_addr(&self) -> u16 {
+        self.addr
+    }
+
+    pub fn get_handle(&self) -> &Arc<I2cHandle<B>> {
+        &self.i2c
+    }
+
+    // adjacent operations of the same kind are merged (no restart between them as
+    // embedded-hal requires), the whole list runs as one I2C_RDWR transaction
+    fn run(&self, address: u16, operations: &mut [Operation<'_>]) -> Result<(), I2cError> {
+        if address != self.addr {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{:#02x} outside device view addr:{:#02x}",
+                address, self.addr
+            )));
+        }
+
+        let mut msgs: Vec<I2cMsg> = Vec::new();
+        for operation in operations.iter() {
+            let is_read = matches!(operation, Operation::Read(_));
+            let merge = msgs.last_mut().filter(|msg| msg.is_read() == is_read);
+            match (operation, merge) {
+                (Operation::Write(data), Some(msg)) => msg.buf.extend_from_slice(data),
+                (Operation::Write(data), None) => msgs.push(I2cMsg::write(address, data)),
+                (Operation::Read(buffer), Some(msg)) => msg.buf.resize(msg.buf.len() + buffer.len(), 0),
+                (Operation::Read(buffer), None) => msgs.push(I2cMsg::read(address, buffer.len())),
+            }
+        }
+
+        // zero length reads are not accepted by i2c-dev, nothing to transfer for them
+        let mut msgs: Vec<I2cMsg> = msgs
+            .into_iter()
+            .filter(|msg| !(msg.is_read() && msg.buf.is_empty()))
+            .collect();
+        if msgs.is_empty() {
+            return Ok(());
+        }
+        if self.i2c.get_addr_cfg(address as u32).mode == I2cAddrMode::BIT10 {
+            msgs = msgs.into_iter().map(|msg| msg.ten_bit()).collect();
+        }
+
+        // spread merged read segments back into caller buffers
+        let mut segments = self.i2c.transfer(&msgs)?.into_iter().flatten();
+        for operation in operations.iter_mut() {
+            if let Operation::Read(buffer) = operation {
+                for byte in buffer.iter_mut() {
+                    *byte = segments.next().unwrap_or_default();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: I2cBackend> hal::ErrorType for I2cHalDevice<B> {
+    type Error = I2cError;
+}
+
+impl<B: I2cBackend> hal::I2c<hal::SevenBitAddress> for I2cHalDevice<B> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), I2cError> {
+        self.run(address as u16, operations)
+    }
+}
+
+impl<B: I2cBackend> hal::I2c<hal::TenBitAddress> for I2cHalDevice<B> {
+    fn transaction(&mut self, address: u16, operations: &mut [Operation<'_>]) -> Result<(), I2cError> {
+        self.run(address, operations)
+    }
+}
+
+// async flavor runs the transaction inline, i2c-dev calls do not yield
+#[cfg(feature = "embedded-hal-async")]
+impl<B: I2cBackend> embedded_hal_async::i2c::I2c<hal::SevenBitAddress> for I2cHalDevice<B> {
+    async fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), I2cError> {
+        self.run(address as u16, operations)
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<B: I2cBackend> embedded_hal_async::i2c::I2c<hal::TenBitAddress> for I2cHalDevice<B> {
+    async fn transaction(&mut self, address: u16, operations: &mut [Operation<'_>]) -> Result<(), I2cError> {
+        self.run(address, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::I2c;
+    use std::sync::Mutex;
+
+    // device view plus the I2C_RDWR segment count of each traced transaction
+    fn sim_device() -> (I2cHalDevice<I2cSimBus>, Arc<Mutex<Vec<I2cTraceKind>>>) {
+        let bus = I2cSimBus::new("sim-hal")
+            .add_device(I2cSimDevice::new(0x20).set_registers(0x00, &[0x5A, 0x11, 0x22, 0x33]));
+        let i2c = Arc::new(I2cHandle::from_backend(bus).unwrap());
+        let kinds = Arc::new(Mutex::new(Vec::new()));
+        let sink = kinds.clone();
+        i2c.set_tracer(Some(Box::new(move |event: &I2cTraceEvent| {
+            sink.lock().unwrap().push(event.kind);
+        })));
+        (I2cHalDevice::new(i2c, 0x20).unwrap(), kinds)
+    }
+
+    #[test]
+    fn write_read_uses_repeated_start() {
+        let (mut device, kinds) = sim_device();
+        let mut buffer = [0u8; 2];
+        device.write_read(0x20u8, &[0x01], &mut buffer).unwrap();
+        assert_eq!(buffer, [0x11, 0x22]);
+        assert_eq!(*kinds.lock().unwrap(), vec![I2cTraceKind::I2C_RDWR(2)]);
+
+        let mut buffer = [0u8; 1];
+        assert!(matches!(
+            device.write_read(0x21u8, &[0x00], &mut buffer),
+            Err(I2cError::INVALID_ARG(_))
+        ));
+    }
+
+    #[test]
+    fn adjacent_operations_are_merged() {
+        let (mut device, kinds) = sim_device();
+
+        // two writes become one segment: pointer 0x01 then 0xAA, 0xBB
+        device
+            .transaction(0x20u8, &mut [Operation::Write(&[0x01]), Operation::Write(&[0xAA, 0xBB])])
+            .unwrap();
+
+        // two reads become one segment spread back into both buffers
+        let mut first = [0u8; 1];
+        let mut second = [0u8; 2];
+        device
+            .transaction(
+                0x20u8,
+                &mut [
+                    Operation::Write(&[0x00]),
+                    Operation::Read(&mut first),
+                    Operation::Read(&mut second),
+                ],
+            )
+            .unwrap();
+        assert_eq!(first, [0x5A]);
+        assert_eq!(second, [0xAA, 0xBB]);
+        assert_eq!(
+            *kinds.lock().unwrap(),
+            vec![I2cTraceKind::I2C_RDWR(1), I2cTraceKind::I2C_RDWR(2)]
+        );
+    }
+
+    #[test]
+    fn empty_read_splits_writes() {
+        let (mut device, kinds) = sim_device();
+
+        // dropped zero length read leaves two write segments with a restart between them,
+        // each one starting with its own register pointer
+        device
+            .transaction(
+                0x20u8,
+                &mut [
+                    Operation::Write(&[0x01, 0xA1]),
+                    Operation::Read(&mut []),
+                    Operation::Write(&[0x03, 0xA3]),
+                ],
+            )
+            .unwrap();
+        assert_eq!(*kinds.lock().unwrap(), vec![I2cTraceKind::I2C_RDWR(2)]);
+
+        let mut buffer = [0u8; 4];
+        device.write_read(0x20u8, &[0x00], &mut buffer).unwrap();
+        assert_eq!(buffer, [0x5A, 0xA1, 0x22, 0xA3]);
+
+        // nothing left to transfer
+        device.transaction(0x20u8, &mut [Operation::Read(&mut [])]).unwrap();
+        assert_eq!(kinds.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::prelude::*;
+use std::time::{Duration, Instant};
+
+// reopen policy on fatal bus errors (ENODEV, EBADF) or after eio_threshold consecutive
+// EIO (0 disables), reopen delay doubles after each failed attempt up to max_delay
+// and resets once the bus is back
+#[derive(Clone, Copy, Debug)]
+pub struct I2cRecovery {
+    pub delay: Duration,
+    pub max_delay: Duration,
+    pub eio_threshold: u32,
+}
+
+impl Default for I2cRecovery {
+    fn default() -> Self {
+        I2cRecovery {
+            delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            eio_threshold: 3,
+        }
+    }
+}
+
+// RECOVERED is only reported to state hook, bus is READY once hook returns
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum I2cBusState {
+    READY,
+    DEGRADED,
+    RECOVERED,
+}
+
+// called on state change, RECOVERED hook is the place to re-apply device init
+pub type I2cStateHook<B> = Box<dyn Fn(&I2cHandle<B>, I2cBusState) + Send + Sync>;
+
+pub(crate) struct I2cRecoveryCtx {
+    pub(crate) policy: Option<I2cRecovery>,
+    pub(crate) state: I2cBusState,
+    pub(crate) delay: Duration,
+    pub(crate) next_attempt: Instant,
+    pub(crate) eio_count: u32,
+}
+
+impl I2cRecoveryCtx {
+    pub(crate) fn new() -> I2cRecoveryCtx {
+        I2cRecoveryCtx {
+            policy: None,
+            state: I2cBusState::READY,
+            delay: Duration::ZERO,
+            next_attempt: Instant::now(),
+            eio_count: 0,
+        }
+    }
+
+    // failed reopen, schedule next attempt with exponential backoff
+    pub(crate) fn backoff(&mut self) {
+        let policy = self.policy.unwrap_or_default();
+        self.state = I2cBusState::DEGRADED;
+        self.delay = if self.delay.is_zero() {
+            policy.delay
+        } else {
+            (self.delay * 2).min(policy.max_delay)
+        };
+        self.next_attempt = Instant::now() + self.delay;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.state = I2cBusState::READY;
+        self.delay = Duration::ZERO;
+        self.next_attempt = Instant::now();
+        self.eio_count = 0;
+    }
+}
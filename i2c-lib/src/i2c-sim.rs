@@ -0,0 +1,763 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::prelude::*;
+#[cfg(feature = "afbv4")]
+use afbv4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// mimic kernel strerror for a device not acknowledging its address
+const SIM_NACK_ERROR: &str = "No such device or address";
+const SIM_IO_ERROR: &str = "simulated adapter failure";
+
+// register map of a simulated device, side effect hooks receive it mutably
+// 8bit pointer devices hold 256 registers, 16bit ones 64K
+pub struct I2cSimRegs {
+    values: Vec<u8>,
+    readonly: Vec<u8>,
+    blocks: HashMap<u8, Vec<u8>>,
+    pointer: u16,
+}
+
+impl I2cSimRegs {
+    pub fn get(&self, reg: u8) -> u8 {
+        self.values[reg as usize]
+    }
+
+    // bypass read-only mask, used by hooks to emulate device internal state
+    pub fn set(&mut self, reg: u8, value: u8) {
+        self.values[reg as usize] = value;
+    }
+
+    pub fn get_at(&self, reg: u16) -> u8 {
+        self.values.get(reg as usize).copied().unwrap_or_default()
+    }
+
+    pub fn set_at(&mut self, reg: u16, value: u8) {
+        if let Some(slot) = self.values.get_mut(reg as usize) {
+            *slot = value;
+        }
+    }
+
+    // register pointer auto-increment wraps within the register map
+    fn next(&self, reg: u16) -> u16 {
+        ((reg as usize + 1) % self.values.len()) as u16
+    }
+
+    pub fn get_block(&self, cmd: u8) -> Vec<u8> {
+        match self.blocks.get(&cmd) {
+            Some(block) => block.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn set_block(&mut self, cmd: u8, values: &[u8]) {
+        self.blocks.insert(cmd, values.to_vec());
+    }
+
+    // bus side write, only bits outside of readonly mask are updated
+    fn write(&mut self, reg: u16, value: u8) {
+        let mask = self.readonly.get(reg as usize).copied().unwrap_or_default();
+        let current = self.get_at(reg);
+        self.set_at(reg, (current & mask) | (value & !mask));
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum I2cSimNack {
+    NEVER,
+    ALWAYS,
+    // nack the next n transactions then behave normally
+    COUNT(u32),
+    // nack any transaction touching one of those registers (0x00-0xFF)
+    REGISTERS(Vec<u8>),
+}
+
+pub type I2cSimHook = Box<dyn Fn(&mut I2cSimRegs, u8) + Send>;
+
+pub struct I2cSimDevice {
+    addr: u32,
+    regs: I2cSimRegs,
+    reg_width: I2cRegWidth,
+    reg_endian: I2cEndian,
+    nack: I2cSimNack,
+    bad_pec: bool,
+    io_errors: (i32, u32),
+    driver: Option<&'static str>,
+    on_read: HashMap<u8, I2cSimHook>,
+    on_write: HashMap<u8, I2cSimHook>,
+}
+
+impl I2cSimDevice {
+    pub fn new(addr: u32) -> I2cSimDevice {
+        I2cSimDevice {
+            addr,
+            regs: I2cSimRegs {
+                values: vec![0; 256],
+                readonly: vec![0; 256],
+                blocks: HashMap::new(),
+                pointer: 0,
+            },
+            reg_width: I2cRegWidth::BIT8,
+            reg_endian: I2cEndian::BIG,
+            nack: I2cSimNack::NEVER,
+            bad_pec: false,
+            io_errors: (0, 0),
+            driver: None,
+            on_read: HashMap::new(),
+            on_write: HashMap::new(),
+        }
+    }
+
+    pub fn set_register(mut self, reg: u8, value: u8) -> Self {
+        self.regs.set(reg, value);
+        self
+    }
+
+    pub fn set_registers(mut self, start: u8, values: &[u8]) -> Self {
+        for (idx, value) in values.iter().enumerate() {
+            self.regs.set(start.wrapping_add(idx as u8), *value);
+        }
+        self
+    }
+
+    // register pointer sent ahead of raw transfers (eg: 16bit for 24C32 eeproms),
+    // SMBus command byte still selects registers 0x00-0xFF
+    pub fn set_reg_width(mut self, width: I2cRegWidth, endian: I2cEndian) -> Self {
+        let size = match width {
+            I2cRegWidth::BIT8 => 0x100,
+            I2cRegWidth::BIT16 => 0x10000,
+        };
+        self.regs.values.resize(size, 0);
+        self.regs.readonly.resize(size, 0);
+        self.reg_width = width;
+        self.reg_endian = endian;
+        self
+    }
+
+    pub fn set_registers_at(mut self, start: u16, values: &[u8]) -> Self {
+        let mut reg = start;
+        for value in values {
+            self.regs.set_at(reg, *value);
+            reg = self.regs.next(reg);
+        }
+        self
+    }
+
+    // bits set within mask cannot be changed from the bus
+    pub fn set_readonly(mut self, reg: u8, mask: u8) -> Self {
+        self.regs.readonly[reg as usize] = mask;
+        self
+    }
+
+    pub fn set_block(mut self, cmd: u8, values: &[u8]) -> Self {
+        self.regs.set_block(cmd, values);
+        self
+    }
+
+    pub fn set_nack(mut self, nack: I2cSimNack) -> Self {
+        self.nack = nack;
+        self
+    }
+
+    // corrupt PEC byte of every transaction done with PEC enabled
+    pub fn set_bad_pec(mut self, bad_pec: bool) -> Self {
+        self.bad_pec = bad_pec;
+        self
+    }
+
+    // fail the next n transactions with errno (eg: EIO wedged adapter, ENODEV unplugged)
+    pub fn set_io_errors(mut self, errno: i32, count: u32) -> Self {
+        self.io_errors = (errno, count);
+        self
+    }
+
+    // emulate a kernel driver bound to this address (I2C_SLAVE returns EBUSY)
+    pub fn set_driver(mut self, driver: &'static str) -> Self {
+        self.driver = Some(driver);
+        self
+    }
+
+    // hook called after register was read from the bus (eg: clear on read)
+    pub fn on_read<F>(mut self, reg: u8, hook: F) -> Self
+    where
+        F: Fn(&mut I2cSimRegs, u8) + Send + 'static,
+    {
+        self.on_read.insert(reg, Box::new(hook));
+        self
+    }
+
+    // hook called after register was written from the bus with written value
+    pub fn on_write<F>(mut self, reg: u8, hook: F) -> Self
+    where
+        F: Fn(&mut I2cSimRegs, u8) + Send + 'static,
+    {
+        self.on_write.insert(reg, Box::new(hook));
+        self
+    }
+
+    pub fn get_addr(&self) -> u32 {
+        self.addr
+    }
+
+    pub fn get_regs(&self) -> &I2cSimRegs {
+        &self.regs
+    }
+
+    fn check_nack(&mut self, regs: &[u16]) -> Result<(), I2cError> {
+        let (errno, count) = &mut self.io_errors;
+        if *count > 0 {
+            *count -= 1;
+            return Err(I2cError::IO(*errno, SIM_IO_ERROR.to_string()));
+        }
+        let nack = match &mut self.nack {
+            I2cSimNack::NEVER => false,
+            I2cSimNack::ALWAYS => true,
+            I2cSimNack::COUNT(count) => {
+                if *count > 0 {
+                    *count -= 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            I2cSimNack::REGISTERS(nacks) => regs
+                .iter()
+                .any(|reg| *reg <= 0xFF && nacks.contains(&(*reg as u8))),
+        };
+        if nack {
+            return Err(I2cError::NO_ACK(SIM_NACK_ERROR.to_string()));
+        }
+        Ok(())
+    }
+
+    // hooks are attached to registers 0x00-0xFF
+    fn get_hook(hooks: &HashMap<u8, I2cSimHook>, reg: u16) -> Option<&I2cSimHook> {
+        u8::try_from(reg).ok().and_then(|reg| hooks.get(&reg))
+    }
+
+    fn read_reg(&mut self, reg: u16) -> u8 {
+        let value = self.regs.get_at(reg);
+        if let Some(hook) = Self::get_hook(&self.on_read, reg) {
+            hook(&mut self.regs, value);
+        }
+        value
+    }
+
+    fn write_reg(&mut self, reg: u16, value: u8) {
+        self.regs.write(reg, value);
+        if let Some(hook) = Self::get_hook(&self.on_write, reg) {
+            hook(&mut self.regs, value);
+        }
+    }
+
+    // read from current register pointer with auto-increment
+    fn read_next(&mut self) -> u8 {
+        let reg = self.regs.pointer;
+        self.regs.pointer = self.regs.next(reg);
+        self.read_reg(reg)
+    }
+
+    fn write_next(&mut self, value: u8) {
+        let reg = self.regs.pointer;
+        self.regs.pointer = self.regs.next(reg);
+        self.write_reg(reg, value);
+    }
+
+    // registers touched by len accesses from start
+    fn span(&self, start: u16, len: usize) -> Vec<u16> {
+        let mut regs = Vec::with_capacity(len);
+        let mut reg = start;
+        for _ in 0..len {
+            regs.push(reg);
+            reg = self.regs.next(reg);
+        }
+        regs
+    }
+
+    fn smbus_xfer(
+        &mut self,
+        rw: SmbusRw,
+        command: u8,
+        size: SmbusSize,
+        data: SmbusData,
+    ) -> Result<SmbusData, I2cError> {
+        let reg = command as u16;
+        let touched = match (size, &data) {
+            (SmbusSize::QUICK, _) => Vec::new(),
+            (SmbusSize::BYTE, _) => Vec::from([self.regs.pointer]),
+            (SmbusSize::WORD_DATA, _) | (SmbusSize::PROC_CALL, _) => self.span(reg, 2),
+            (SmbusSize::I2C_BLOCK_DATA, SmbusData::BLOCK(values)) => self.span(reg, values.len()),
+            _ => Vec::from([reg]),
+        };
+        self.check_nack(&touched)?;
+
+        let response = match (rw, size, data) {
+            (_, SmbusSize::QUICK, _) => SmbusData::NONE,
+            (SmbusRw::READ, SmbusSize::BYTE, _) => SmbusData::BYTE(self.read_next()),
+            (SmbusRw::WRITE, SmbusSize::BYTE, _) => {
+                // send byte: command is used as new register pointer
+                self.regs.pointer = reg;
+                SmbusData::NONE
+            }
+            (SmbusRw::READ, SmbusSize::BYTE_DATA, _) => SmbusData::BYTE(self.read_reg(reg)),
+            (SmbusRw::WRITE, SmbusSize::BYTE_DATA, SmbusData::BYTE(value)) => {
+                self.write_reg(reg, value);
+                SmbusData::NONE
+            }
+            (SmbusRw::READ, SmbusSize::WORD_DATA, _) => {
+                let low = self.read_reg(reg) as u16;
+                let high = self.read_reg(self.regs.next(reg)) as u16;
+                SmbusData::WORD(low | (high << 8))
+            }
+            (SmbusRw::WRITE, SmbusSize::WORD_DATA, SmbusData::WORD(value)) => {
+                self.write_reg(reg, (value & 0xFF) as u8);
+                self.write_reg(self.regs.next(reg), (value >> 8) as u8);
+                SmbusData::NONE
+            }
+            (SmbusRw::WRITE, SmbusSize::PROC_CALL, SmbusData::WORD(value)) => {
+                self.write_reg(reg, (value & 0xFF) as u8);
+                self.write_reg(self.regs.next(reg), (value >> 8) as u8);
+                let low = self.read_reg(reg) as u16;
+                let high = self.read_reg(self.regs.next(reg)) as u16;
+                SmbusData::WORD(low | (high << 8))
+            }
+            (SmbusRw::READ, SmbusSize::BLOCK_DATA, _) => {
+                let mut block = self.regs.get_block(command);
+                block.truncate(I2C_SMBUS_BLOCK_MAX);
+                SmbusData::BLOCK(block)
+            }
+            (SmbusRw::WRITE, SmbusSize::BLOCK_DATA, SmbusData::BLOCK(values)) => {
+                self.regs.set_block(command, &values);
+                SmbusData::NONE
+            }
+            (SmbusRw::WRITE, SmbusSize::BLOCK_PROC_CALL, SmbusData::BLOCK(values)) => {
+                self.regs.set_block(command, &values);
+                SmbusData::BLOCK(self.regs.get_block(command))
+            }
+            (SmbusRw::READ, SmbusSize::I2C_BLOCK_DATA, SmbusData::BLOCK(values)) => {
+                self.regs.pointer = reg;
+                SmbusData::BLOCK(values.iter().map(|_| self.read_next()).collect())
+            }
+            (SmbusRw::WRITE, SmbusSize::I2C_BLOCK_DATA, SmbusData::BLOCK(values)) => {
+                self.regs.pointer = reg;
+                for value in values {
+                    self.write_next(value);
+                }
+                SmbusData::NONE
+            }
+            (rw, size, data) => {
+                return Err(I2cError::UNSUPPORTED(format!(
+                    "simulator unsupported rw:{:?} size:{:?} data:{:?}",
+                    rw, size, data
+                )))
+            }
+        };
+        Ok(response)
+    }
+
+    // raw i2c segment, a write starts with the register pointer (1 or 2 bytes following
+    // reg-width) unless segment continues previous one (no-start): all bytes are data
+    fn transfer(&mut self, msg: &mut I2cMsg) -> Result<(), I2cError> {
+        if msg.is_read() || msg.flags & I2C_M_NOSTART != 0 {
+            let touched = self.span(self.regs.pointer, msg.buf.len());
+            self.check_nack(&touched)?;
+            if msg.is_read() {
+                for idx in 0..msg.buf.len() {
+                    msg.buf[idx] = self.read_next();
+                }
+            } else {
+                for idx in 0..msg.buf.len() {
+                    self.write_next(msg.buf[idx]);
+                }
+            }
+            return Ok(());
+        }
+
+        let width = match self.reg_width {
+            I2cRegWidth::BIT8 => 1,
+            I2cRegWidth::BIT16 => 2,
+        };
+        if msg.buf.is_empty() {
+            return self.check_nack(&[]);
+        }
+        if msg.buf.len() < width {
+            return Err(I2cError::INVALID_ARG(format!(
+                "addr:{:#02x} truncated {}byte register pointer",
+                self.addr, width
+            )));
+        }
+        let reg = match (width, self.reg_endian) {
+            (1, _) => msg.buf[0] as u16,
+            (_, I2cEndian::BIG) => u16::from_be_bytes([msg.buf[0], msg.buf[1]]),
+            (_, I2cEndian::LITTLE) => u16::from_le_bytes([msg.buf[0], msg.buf[1]]),
+        };
+        let touched = self.span(reg, (msg.buf.len() - width).max(1));
+        self.check_nack(&touched)?;
+        self.regs.pointer = reg;
+        for idx in width..msg.buf.len() {
+            self.write_next(msg.buf[idx]);
+        }
+        Ok(())
+    }
+}
+
+// in-memory bus backend, devices are addressed exactly as on a real bus
+pub struct I2cSimBus {
+    name: &'static str,
+    devices: RefCell<HashMap<u32, I2cSimDevice>>,
+    funcs: I2cFuncs,
+    addr: Cell<u32>,
+    tenbit: Cell<bool>,
+    pec: Cell<bool>,
+    opened: Cell<bool>,
+}
+
+impl I2cSimBus {
+    pub fn new(name: &'static str) -> I2cSimBus {
+        I2cSimBus {
+            name,
+            devices: RefCell::new(HashMap::new()),
+            funcs: I2cFuncs::all(),
+            addr: Cell::new(0),
+            tenbit: Cell::new(false),
+            pec: Cell::new(false),
+            opened: Cell::new(false),
+        }
+    }
+
+    pub fn add_device(self, device: I2cSimDevice) -> Self {
+        self.devices.borrow_mut().insert(device.addr, device);
+        self
+    }
+
+    // emulate a restricted adapter (eg: SMBus only controller)
+    pub fn set_funcs(mut self, funcs: I2cFuncs) -> Self {
+        self.funcs = funcs;
+        self
+    }
+
+    // give access to a device register map (test assertions, fault injection)
+    pub fn with_device<F, R>(&self, addr: u32, callback: F) -> Option<R>
+    where
+        F: FnOnce(&mut I2cSimDevice) -> R,
+    {
+        self.devices.borrow_mut().get_mut(&addr).map(callback)
+    }
+
+    fn check_open(&self) -> Result<(), I2cError> {
+        if !self.opened.get() {
+            return Err(I2cError::IO(cglue::EBADF, format!("simulated bus:{} not open", self.name)));
+        }
+        Ok(())
+    }
+}
+
+impl I2cBackend for I2cSimBus {
+    fn open(&self) -> Result<(), I2cError> {
+        self.opened.set(true);
+        #[cfg(feature = "afbv4")]
+        afb_log_msg!(Debug, None, "Open simulated bus={}", self.name);
+        Ok(())
+    }
+
+    fn close(&self) {
+        self.opened.set(false);
+    }
+
+    fn get_name(&self) -> &str {
+        self.name
+    }
+
+    fn get_funcs(&self) -> Result<I2cFuncs, I2cError> {
+        Ok(self.funcs)
+    }
+
+    fn set_timeout(&self, _timeout: Duration) -> Result<(), I2cError> {
+        self.check_open()
+    }
+
+    fn set_retries(&self, _retries: u32) -> Result<(), I2cError> {
+        self.check_open()
+    }
+
+    fn set_tenbit(&self, enable: bool) -> Result<(), I2cError> {
+        self.check_open()?;
+        self.tenbit.set(enable);
+        Ok(())
+    }
+
+    // same range and busy checks as kernel I2C_SLAVE
+    fn set_address(&self, addr: u32, force: bool) -> Result<(), I2cError> {
+        self.check_open()?;
+        let max_addr = if self.tenbit.get() { 0x3FF } else { 0x7F };
+        if addr > max_addr {
+            return Err(I2cError::INVALID_ARG(format!("addr:{:#02x} out of range", addr)));
+        }
+        if !force && self.get_driver(addr).is_some() {
+            return Err(I2cError::ADDR_BUSY(format!("addr:{:#02x} claimed by a kernel driver", addr)));
+        }
+        self.addr.set(addr);
+        Ok(())
+    }
+
+    fn get_driver(&self, addr: u32) -> Option<String> {
+        let devices = self.devices.borrow();
+        let driver = devices.get(&addr)?.driver?;
+        Some(driver.to_string())
+    }
+
+    fn set_pec(&self, enable: bool) -> Result<(), I2cError> {
+        self.check_open()?;
+        self.pec.set(enable);
+        Ok(())
+    }
+
+    fn smbus_xfer(
+        &self,
+        rw: SmbusRw,
+        command: u8,
+        size: SmbusSize,
+        data: SmbusData,
+    ) -> Result<SmbusData, I2cError> {
+        self.check_open()?;
+        match self.devices.borrow_mut().get_mut(&self.addr.get()) {
+            Some(device) => {
+                let response = device.smbus_xfer(rw, command, size, data)?;
+                if self.pec.get() && device.bad_pec {
+                    return Err(I2cError::PEC("PEC checksum mismatch".to_string()));
+                }
+                Ok(response)
+            }
+            None => Err(I2cError::NO_ACK(SIM_NACK_ERROR.to_string())),
+        }
+    }
+
+    fn transfer(&self, msgs: &mut [I2cMsg]) -> Result<(), I2cError> {
+        self.check_open()?;
+        let mut devices = self.devices.borrow_mut();
+        for msg in msgs.iter_mut() {
+            let status = match devices.get_mut(&(msg.addr as u32)) {
+                Some(device) => device.transfer(msg),
+                None => Err(I2cError::NO_ACK(SIM_NACK_ERROR.to_string())),
+            };
+            if msg.flags & I2C_M_IGNORE_NAK == 0 {
+                status?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn sim_handle() -> I2cHandle<I2cSimBus> {
+        let bus = I2cSimBus::new("sim-test")
+            .add_device(
+                I2cSimDevice::new(0x20)
+                    .set_registers(0x00, &[0x5A, 0x00, 0x00, 0xC3])
+                    .set_block(0x10, &[0x01, 0x02, 0x03]),
+            )
+            .add_device(I2cSimDevice::new(0x21).set_nack(I2cSimNack::ALWAYS))
+            .add_device(I2cSimDevice::new(0x50).set_driver("at24"));
+        I2cHandle::from_backend(bus).unwrap()
+    }
+
+    #[test]
+    fn byte_word_block_roundtrip() {
+        let i2c = sim_handle();
+        assert_eq!(i2c.read::<u8>(0x20, 0x00).unwrap(), 0x5A);
+
+        i2c.write(0x20, 0x01, 0xA5u8).unwrap();
+        assert_eq!(i2c.read::<u8>(0x20, 0x01).unwrap(), 0xA5);
+
+        // SMBus words are little-endian, low byte lands in the first register
+        i2c.write(0x20, 0x04, 0x1234u16).unwrap();
+        assert_eq!(i2c.read::<u16>(0x20, 0x04).unwrap(), 0x1234);
+        assert_eq!(i2c.read::<u8>(0x20, 0x04).unwrap(), 0x34);
+
+        assert_eq!(i2c.read_block(0x20, 0x10).unwrap(), vec![0x01, 0x02, 0x03]);
+        i2c.write_block(0x20, 0x11, &[0x0A, 0x0B]).unwrap();
+        assert_eq!(i2c.read_block(0x20, 0x11).unwrap(), vec![0x0A, 0x0B]);
+
+        i2c.write_i2c_block(0x20, 0x20, &[0x10, 0x20, 0x30]).unwrap();
+        assert_eq!(i2c.read_i2c_block(0x20, 0x20, 3).unwrap(), vec![0x10, 0x20, 0x30]);
+        assert_eq!(i2c.read::<u8>(0x20, 0x22).unwrap(), 0x30);
+    }
+
+    #[test]
+    fn nack_and_busy_errors() {
+        let i2c = sim_handle();
+        assert!(matches!(i2c.read::<u8>(0x21, 0x00), Err(I2cError::NO_ACK(_))));
+        assert!(matches!(i2c.write(0x22, 0x00, 0x01u8), Err(I2cError::NO_ACK(_))));
+
+        assert!(matches!(i2c.read::<u8>(0x50, 0x00), Err(I2cError::ADDR_BUSY(_))));
+        i2c.set_force(0x50, true);
+        assert_eq!(i2c.read::<u8>(0x50, 0x00).unwrap(), 0x00);
+    }
+
+    #[test]
+    fn scan_reports_found_and_busy() {
+        let i2c = sim_handle();
+        let result = i2c.scan(0x08, 0x77, I2cScanMode::AUTO).unwrap();
+        assert_eq!(result.found, vec![0x20]);
+        assert_eq!(result.busy, vec![0x50]);
+
+        // a probe failing with a fatal error is not an empty address
+        let bus = I2cSimBus::new("sim-test").add_device(I2cSimDevice::new(0x20).set_io_errors(cglue::ENODEV, 1));
+        let i2c = I2cHandle::from_backend(bus).unwrap();
+        assert!(matches!(i2c.scan(0x08, 0x77, I2cScanMode::AUTO), Err(I2cError::IO(cglue::ENODEV, _))));
+    }
+
+    #[test]
+    fn dump_skips_unreadable_registers() {
+        let i2c = sim_handle();
+        let registers = i2c.dump(0x20, 0x00..=0x03, I2cDumpMode::BYTE).unwrap();
+        assert_eq!(
+            registers,
+            vec![
+                (0x00, Some(SmbusData::BYTE(0x5A))),
+                (0x01, Some(SmbusData::BYTE(0x00))),
+                (0x02, Some(SmbusData::BYTE(0x00))),
+                (0x03, Some(SmbusData::BYTE(0xC3))),
+            ]
+        );
+
+        // unreadable registers are reported as i2cdump 'XX'
+        let registers = i2c.dump(0x21, 0x00..=0x01, I2cDumpMode::BYTE).unwrap();
+        assert_eq!(registers, vec![(0x00, None), (0x01, None)]);
+    }
+
+    #[test]
+    fn update_bits_only_touch_mask() {
+        let i2c = sim_handle();
+        assert_eq!(i2c.update_bits(0x20, 0x03, 0x30u8, 0x20u8).unwrap(), 0xE3);
+        assert_eq!(i2c.read::<u8>(0x20, 0x03).unwrap(), 0xE3);
+
+        assert_eq!(i2c.clear_bit(0x20, 0x03, 7).unwrap(), 0x63);
+        assert_eq!(i2c.set_bit(0x20, 0x03, 2).unwrap(), 0x67);
+        assert_eq!(i2c.toggle_bit(0x20, 0x03, 0).unwrap(), 0x66);
+        assert!(matches!(i2c.set_bit(0x20, 0x03, 8), Err(I2cError::INVALID_ARG(_))));
+    }
+
+    #[test]
+    fn transfer_is_traced() {
+        let i2c = sim_handle();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        i2c.set_tracer(Some(Box::new(move |event: &I2cTraceEvent| {
+            sink.lock().unwrap().push((event.addr, event.reg, event.kind, event.value.clone()));
+        })));
+
+        let msgs = [I2cMsg::write(0x20, &[0x00]), I2cMsg::read(0x20, 2)];
+        assert_eq!(i2c.transfer(&msgs).unwrap(), vec![vec![0x5A, 0x00]]);
+        i2c.write_at(0x20, 0x01, &[0x11]).unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![
+                (0x20, None, I2cTraceKind::I2C_RDWR(2), SmbusData::BLOCK(vec![0x5A, 0x00])),
+                (0x20, Some(0x01), I2cTraceKind::I2C_RDWR(1), SmbusData::BLOCK(vec![0x01, 0x11])),
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_bus_is_recovered() {
+        let i2c = sim_handle();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let sink = states.clone();
+        i2c.set_recovery(Some(I2cRecovery::default()));
+        i2c.set_state_hook(Some(Box::new(move |_: &I2cHandle<I2cSimBus>, state| {
+            sink.lock().unwrap().push(state);
+        })));
+
+        // failing request is replayed once on the reopened bus
+        i2c.with_backend(|bus| bus.close());
+        assert_eq!(i2c.read::<u8>(0x20, 0x00).unwrap(), 0x5A);
+        assert_eq!(*states.lock().unwrap(), vec![I2cBusState::RECOVERED]);
+        assert_eq!(i2c.get_state(), I2cBusState::READY);
+
+        // a nack is a device error, the bus is left alone
+        assert!(i2c.read::<u8>(0x21, 0x00).is_err());
+        assert_eq!(states.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn typed_values_follow_endian() {
+        let i2c = sim_handle();
+        i2c.write_value(0x20, 0x04, I2cDataType::I16, I2cEndian::BIG, -10).unwrap();
+        let regs = i2c.with_backend(|bus| bus.with_device(0x20, |device| device.get_regs().get(0x04)));
+        assert_eq!(regs, Some(0xFF));
+        assert_eq!(i2c.read_value(0x20, 0x04, I2cDataType::I16, I2cEndian::BIG).unwrap(), -10);
+
+        i2c.write_value(0x20, 0x08, I2cDataType::U32, I2cEndian::LITTLE, 0x01020304).unwrap();
+        assert_eq!(i2c.read_at(0x20, 0x08, 4).unwrap(), vec![0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(i2c.read_value(0x20, 0x08, I2cDataType::U32, I2cEndian::LITTLE).unwrap(), 0x01020304);
+        assert!(i2c.write_value(0x20, 0x08, I2cDataType::U8, I2cEndian::LITTLE, 256).is_err());
+    }
+
+    #[test]
+    fn consecutive_eio_reopen_bus() {
+        let bus = I2cSimBus::new("sim-test").add_device(I2cSimDevice::new(0x20).set_io_errors(cglue::EIO, 3));
+        let i2c = I2cHandle::from_backend(bus).unwrap();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let sink = states.clone();
+        i2c.set_recovery(Some(I2cRecovery {
+            eio_threshold: 2,
+            ..I2cRecovery::default()
+        }));
+        i2c.set_state_hook(Some(Box::new(move |_: &I2cHandle<I2cSimBus>, state| {
+            sink.lock().unwrap().push(state);
+        })));
+
+        // a single EIO leaves the bus alone
+        assert!(matches!(i2c.read::<u8>(0x20, 0x00), Err(I2cError::IO(cglue::EIO, _))));
+        assert!(states.lock().unwrap().is_empty());
+
+        // threshold reached, bus is reopened but the failed transfer is not replayed
+        assert!(matches!(i2c.read::<u8>(0x20, 0x00), Err(I2cError::IO(cglue::EIO, _))));
+        assert_eq!(*states.lock().unwrap(), vec![I2cBusState::RECOVERED]);
+
+        // count restarts after the reopen
+        assert!(matches!(i2c.read::<u8>(0x20, 0x00), Err(I2cError::IO(cglue::EIO, _))));
+        assert_eq!(states.lock().unwrap().len(), 1);
+        assert_eq!(i2c.read::<u8>(0x20, 0x00).unwrap(), 0x00);
+    }
+
+    #[test]
+    fn sixteen_bit_pointer() {
+        let bus = I2cSimBus::new("sim-test").add_device(
+            I2cSimDevice::new(0x50)
+                .set_reg_width(I2cRegWidth::BIT16, I2cEndian::BIG)
+                .set_registers_at(0x0010, &[0x24, 0x36]),
+        );
+        let i2c = I2cHandle::from_backend(bus).unwrap();
+        i2c.set_reg_width(0x50, I2cRegWidth::BIT16, I2cEndian::BIG).unwrap();
+
+        assert_eq!(i2c.read_at(0x50, 0x0010, 2).unwrap(), vec![0x24, 0x36]);
+        i2c.write_at(0x50, 0x1234, &[0xAB, 0xCD]).unwrap();
+        assert_eq!(i2c.read_at(0x50, 0x1234, 2).unwrap(), vec![0xAB, 0xCD]);
+        let untouched = i2c.with_backend(|bus| bus.with_device(0x50, |device| device.get_regs().get(0x00)));
+        assert_eq!(untouched, Some(0x00));
+
+        // one byte pointer cannot address a 16bit register map
+        let msgs = [I2cMsg::write(0x50, &[0x12])];
+        assert!(matches!(i2c.transfer(&msgs), Err(I2cError::INVALID_ARG(_))));
+    }
+}
@@ -0,0 +1,238 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * References:
+ *  https://docs.kernel.org/i2c/dev-interface.html
+ *  https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-bus-i2c-devices-*
+ *
+ */
+
+use crate::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SYSFS_DEFAULT_ROOT: &str = "/sys";
+const SYSFS_I2C_DEV: &str = "class/i2c-dev";
+const SYSFS_I2C_DEVICES: &str = "bus/i2c/devices";
+
+#[derive(Clone, Debug)]
+pub struct I2cAdapter {
+    pub number: u32,
+    pub name: String,
+    pub devname: String,
+    // kernel driver of the controller behind the adapter (eg: i2c_designware)
+    pub driver: Option<String>,
+}
+
+// module names treat '-' and '_' alike (i2c-designware == i2c_designware)
+fn same_driver(driver: &str, name: &str) -> bool {
+    driver.replace('-', "_") == name.replace('-', "_")
+}
+
+// sysfs explorer, root is configurable to run against a fake directory tree
+#[derive(Clone, Debug)]
+pub struct I2cSysfs {
+    root: PathBuf,
+}
+
+impl Default for I2cSysfs {
+    fn default() -> Self {
+        I2cSysfs::new(SYSFS_DEFAULT_ROOT)
+    }
+}
+
+impl I2cSysfs {
+    pub fn new(root: &str) -> I2cSysfs {
+        I2cSysfs {
+            root: PathBuf::from(root),
+        }
+    }
+
+    pub fn get_root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    // list 'i2c-N' entries of a sysfs directory with their adapter name
+    fn scan_dir(&self, subdir: &str) -> Vec<I2cAdapter> {
+        let mut adapters = Vec::new();
+        let entries = match fs::read_dir(self.root.join(subdir)) {
+            Err(_) => return adapters,
+            Ok(value) => value,
+        };
+
+        for entry in entries.flatten() {
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let number = match filename.strip_prefix("i2c-") {
+                Some(value) => match value.parse::<u32>() {
+                    Ok(number) => number,
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+
+            let name = match fs::read_to_string(entry.path().join("name")) {
+                Ok(value) => value.trim().to_string(),
+                Err(_) => String::new(),
+            };
+
+            adapters.push(I2cAdapter {
+                number,
+                name,
+                devname: format!("/dev/i2c-{}", number),
+                driver: Self::get_adapter_driver(&entry.path()),
+            });
+        }
+        adapters
+    }
+
+    // class/i2c-dev entries link their adapter through 'device', the controller owning
+    // the adapter is its parent directory and holds the bound 'driver' link
+    fn get_adapter_driver(entry: &Path) -> Option<String> {
+        let adapter = match fs::canonicalize(entry.join("device")) {
+            Ok(path) => path,
+            Err(_) => fs::canonicalize(entry).ok()?,
+        };
+        let target = fs::read_link(adapter.parent()?.join("driver")).ok()?;
+        target
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    // adapters with a /dev/i2c-N node first, then raw bus list (i2c-dev not loaded)
+    pub fn get_adapters(&self) -> Result<Vec<I2cAdapter>, I2cError> {
+        let mut adapters = self.scan_dir(SYSFS_I2C_DEV);
+        if adapters.is_empty() {
+            adapters = self.scan_dir(SYSFS_I2C_DEVICES);
+        }
+
+        if adapters.is_empty() {
+            return Err(I2cError::IO(
+                cglue::ENODEV,
+                format!("no i2c adapter found under sysfs root:{:?}", self.root),
+            ));
+        }
+        adapters.sort_by_key(|adapter| adapter.number);
+        Ok(adapters)
+    }
+
+    // exact adapter name match wins, then controller driver name (eg: 'i2c-designware'),
+    // else a unique name prefix (eg: 'Synopsys DesignWare')
+    pub fn find_adapter(&self, name: &str) -> Result<I2cAdapter, I2cError> {
+        let adapters = self.get_adapters()?;
+
+        if let Some(adapter) = adapters.iter().find(|adapter| adapter.name == name) {
+            return Ok(adapter.clone());
+        }
+
+        let mut matches: Vec<&I2cAdapter> = adapters
+            .iter()
+            .filter(|adapter| match &adapter.driver {
+                Some(driver) => same_driver(driver, name),
+                None => false,
+            })
+            .collect();
+        if matches.is_empty() {
+            matches = adapters
+                .iter()
+                .filter(|adapter| adapter.name.starts_with(name))
+                .collect();
+        }
+
+        match matches.len() {
+            1 => Ok(matches[0].clone()),
+            0 => Err(I2cError::IO(
+                cglue::ENODEV,
+                format!("no adapter named:'{}' under sysfs root:{:?}", name, self.root),
+            )),
+            _ => Err(I2cError::INVALID_ARG(format!(
+                "adapter name:'{}' matches {} adapters, use a longer name",
+                name,
+                matches.len()
+            ))),
+        }
+    }
+
+    // driver bound to addr: bus/i2c/devices/N-00AA/driver -> .../drivers/xxx
+    pub fn get_driver(&self, busnum: u32, addr: u32) -> Option<String> {
+        let path = self
+            .root
+            .join(SYSFS_I2C_DEVICES)
+            .join(format!("{}-{:04x}", busnum, addr))
+            .join("driver");
+        let target = fs::read_link(path).ok()?;
+        target
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    // minimal sysfs: two designware controllers and one smbus adapter
+    fn fake_sysfs(tag: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("i2c-sysfs-{}-{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let adapters = [
+            (0, "i2c_designware.0", "Synopsys DesignWare I2C adapter", "i2c_designware"),
+            (1, "i2c_designware.1", "Synopsys DesignWare I2C adapter", "i2c_designware"),
+            (2, "0000:00:1f.4", "SMBus I801 adapter at efa0", "i801_smbus"),
+        ];
+        fs::create_dir_all(root.join(SYSFS_I2C_DEV)).unwrap();
+        for (number, controller, name, driver) in adapters {
+            let ctrl = root.join("devices").join(controller);
+            let adapter = ctrl.join(format!("i2c-{}", number));
+            fs::create_dir_all(&adapter).unwrap();
+            fs::write(adapter.join("name"), format!("{}\n", name)).unwrap();
+            let drivers = root.join("bus/platform/drivers").join(driver);
+            fs::create_dir_all(&drivers).unwrap();
+            symlink(&drivers, ctrl.join("driver")).unwrap();
+
+            let class = root.join(SYSFS_I2C_DEV).join(format!("i2c-{}", number));
+            fs::create_dir_all(&class).unwrap();
+            fs::write(class.join("name"), format!("{}\n", name)).unwrap();
+            symlink(&adapter, class.join("device")).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn find_adapter_by_name_driver_and_prefix() {
+        let root = fake_sysfs("find");
+        let sysfs = I2cSysfs::new(root.to_str().unwrap());
+
+        let adapters = sysfs.get_adapters().unwrap();
+        assert_eq!(adapters.len(), 3);
+        assert_eq!(adapters[0].driver.as_deref(), Some("i2c_designware"));
+
+        let adapter = sysfs.find_adapter("SMBus I801 adapter at efa0").unwrap();
+        assert_eq!(adapter.devname, "/dev/i2c-2");
+        assert_eq!(sysfs.find_adapter("i801-smbus").unwrap().number, 2);
+        assert_eq!(sysfs.find_adapter("SMBus I801").unwrap().number, 2);
+
+        // two controllers share the designware driver and name
+        assert!(matches!(sysfs.find_adapter("i2c-designware"), Err(I2cError::INVALID_ARG(_))));
+        assert!(matches!(sysfs.find_adapter("Synopsys"), Err(I2cError::INVALID_ARG(_))));
+        assert!(matches!(sysfs.find_adapter("i2c-gpio"), Err(I2cError::IO(_, _))));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn unique_driver_resolves_adapter() {
+        let root = fake_sysfs("driver");
+        fs::remove_dir_all(root.join(SYSFS_I2C_DEV).join("i2c-1")).unwrap();
+        let sysfs = I2cSysfs::new(root.to_str().unwrap());
+        assert_eq!(sysfs.find_adapter("i2c-designware").unwrap().devname, "/dev/i2c-0");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright (C) 2015-2022 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::prelude::*;
+use std::fmt;
+use std::time::Duration;
+
+// SMBus ioctl or combined I2C_RDWR transaction with its segment count
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum I2cTraceKind {
+    SMBUS(SmbusSize),
+    I2C_RDWR(usize),
+}
+
+// one transaction as seen by I2cHandle, duration includes bus lock wait
+// reg is None for raw transfers, 16bit registers are traced in full
+#[derive(Clone, Debug)]
+pub struct I2cTraceEvent<'a> {
+    pub bus: &'a str,
+    pub addr: u32,
+    pub reg: Option<u16>,
+    pub rw: SmbusRw,
+    pub kind: I2cTraceKind,
+    pub value: SmbusData,
+    pub duration: Duration,
+    pub error: Option<&'a I2cError>,
+}
+
+impl fmt::Display for I2cTraceEvent<'_> {
+    fn fmt(&self, format: &mut fmt::Formatter) -> fmt::Result {
+        write!(format, "bus:{} addr:{:#02x} ", self.bus, self.addr)?;
+        match self.reg {
+            Some(reg) => write!(format, "reg:{:#02x} ", reg)?,
+            None => write!(format, "reg:none ")?,
+        }
+        match self.kind {
+            I2cTraceKind::SMBUS(size) => write!(format, "rw:{:?} size:{:?} ", self.rw, size)?,
+            I2cTraceKind::I2C_RDWR(count) => write!(format, "rw:{:?} i2c-rdwr msgs:{} ", self.rw, count)?,
+        }
+        match &self.value {
+            SmbusData::NONE => write!(format, "value:none ")?,
+            SmbusData::BYTE(value) => write!(format, "value:{:#02x} ", value)?,
+            SmbusData::WORD(value) => write!(format, "value:{:#04x} ", value)?,
+            SmbusData::BLOCK(values) => write!(format, "value:{:02x?} ", values)?,
+        }
+        write!(format, "duration:{:?} ", self.duration)?;
+        match self.error {
+            None => write!(format, "result:ok"),
+            Some(error) => write!(format, "result:{}", error),
+        }
+    }
+}
+
+// pluggable transaction observer, no sink installed means no event is even built
+pub trait I2cTraceSink: Send + Sync {
+    fn trace(&self, event: &I2cTraceEvent);
+}
+
+impl<F> I2cTraceSink for F
+where
+    F: Fn(&I2cTraceEvent) + Send + Sync,
+{
+    fn trace(&self, event: &I2cTraceEvent) {
+        self(event)
+    }
+}
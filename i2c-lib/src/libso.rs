@@ -15,17 +15,62 @@
     html_favicon_url = "https://iot.bzh/images/defaults/favicon.ico"
 )]
 
-#[cfg(not(afbv4))]
+#[cfg(feature = "afbv4")]
 extern crate afbv4;
 
 #[path = "../capi/capi-mod.rs"]
 mod capi;
 
+#[path = "i2c-error.rs"]
+mod error;
+
+#[path = "i2c-backend.rs"]
+mod backend;
+
+#[path = "i2c-funcs.rs"]
+mod funcs;
+
+#[path = "i2c-trace.rs"]
+mod trace;
+
+#[path = "i2c-sysfs.rs"]
+mod sysfs;
+
+#[path = "i2c-dev.rs"]
+mod dev;
+
+#[path = "i2c-sim.rs"]
+mod sim;
+
+#[path = "i2c-data.rs"]
+mod data;
+
+#[cfg(feature = "embedded-hal")]
+#[path = "i2c-hal.rs"]
+mod hal;
+
+#[path = "i2c-recovery.rs"]
+mod recovery;
+
+#[path = "i2c-async.rs"]
+mod async_io;
+
 #[path = "i2c-bus.rs"]
 mod i2c;
 
-
 pub mod prelude {
     pub(crate) use crate::capi::*;
+    pub use crate::async_io::*;
+    pub use crate::backend::*;
+    pub use crate::data::*;
+    pub use crate::dev::*;
+    pub use crate::error::*;
+    pub use crate::funcs::*;
+    #[cfg(feature = "embedded-hal")]
+    pub use crate::hal::*;
     pub use crate::i2c::*;
+    pub use crate::recovery::*;
+    pub use crate::sim::*;
+    pub use crate::sysfs::*;
+    pub use crate::trace::*;
 }
\ No newline at end of file